@@ -1,10 +1,15 @@
 #![allow(non_camel_case_types)]
 #![allow(clippy::missing_safety_doc)]
 
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, OnceLock, RwLock};
+
 use raqote::{
-    BlendMode, DrawOptions, DrawTarget, LineCap, LineJoin, ExtendMode, FilterMode,
-    Path, PathBuilder, Point, SolidSource, Source, StrokeStyle, Transform, Winding,
-    Color, Gradient, GradientStop, Image, Spread,
+    AntialiasMode, BlendMode, DrawOptions, DrawTarget, LineCap, LineJoin, ExtendMode, FilterMode,
+    Path, PathBuilder, PathOp, Point, SolidSource, Source, StrokeStyle, Transform, Winding,
+    Color, Gradient, GradientStop, Image, Spread, IntPoint, IntRect,
 };
 
 #[repr(C)]
@@ -65,6 +70,15 @@ pub struct rq_rect {
     height: f32,
 }
 
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct rq_int_rect {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct rq_color {
@@ -169,6 +183,84 @@ impl From<rq_filter_mode> for FilterMode {
     }
 }
 
+/// Byte order of a 32-bit pixel in memory and whether color is premultiplied by alpha.
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq)]
+pub enum rq_pixel_format {
+    Bgra8Premultiplied,
+    Bgra8,
+    Rgba8Premultiplied,
+    Rgba8,
+    Argb8Premultiplied,
+    Argb8,
+}
+
+impl rq_pixel_format {
+    // The layout of raqote's native 0xAARRGGBB u32 pixels in memory.
+    fn native() -> Self {
+        if cfg!(target_endian = "little") {
+            rq_pixel_format::Bgra8Premultiplied
+        } else {
+            rq_pixel_format::Argb8Premultiplied
+        }
+    }
+
+    // Byte offsets of the a, r, g and b channels within a pixel.
+    fn channel_offsets(self) -> [usize; 4] {
+        match self {
+            rq_pixel_format::Bgra8Premultiplied | rq_pixel_format::Bgra8 => [3, 2, 1, 0],
+            rq_pixel_format::Rgba8Premultiplied | rq_pixel_format::Rgba8 => [3, 0, 1, 2],
+            rq_pixel_format::Argb8Premultiplied | rq_pixel_format::Argb8 => [0, 1, 2, 3],
+        }
+    }
+
+    fn is_premultiplied(self) -> bool {
+        matches!(
+            self,
+            rq_pixel_format::Bgra8Premultiplied
+                | rq_pixel_format::Rgba8Premultiplied
+                | rq_pixel_format::Argb8Premultiplied
+        )
+    }
+
+    // Converts a row of raqote pixels into `dst`, which holds 4 bytes per pixel.
+    fn convert_row(self, src: &[u32], dst: &mut [u8]) {
+        let [ai, ri, gi, bi] = self.channel_offsets();
+        let premultiplied = self.is_premultiplied();
+
+        for (pixel, out) in src.iter().zip(dst.chunks_exact_mut(4)) {
+            let a = (pixel >> 24) & 0xff;
+            let mut r = (pixel >> 16) & 0xff;
+            let mut g = (pixel >> 8) & 0xff;
+            let mut b = pixel & 0xff;
+
+            if !premultiplied && a != 0 && a != 255 {
+                // Channels above alpha are not valid premultiplied colors, so clamp them
+                // rather than letting them wrap.
+                r = ((r * 255 + a / 2) / a).min(255);
+                g = ((g * 255 + a / 2) / a).min(255);
+                b = ((b * 255 + a / 2) / a).min(255);
+            }
+
+            out[ai] = a as u8;
+            out[ri] = r as u8;
+            out[gi] = g as u8;
+            out[bi] = b as u8;
+        }
+    }
+}
+
+/// A borrowed view of a draw target's pixels. It stays valid until the draw target is
+/// destroyed; drawing through the draw target updates the pixels in place.
+#[repr(C)]
+pub struct rq_pixel_view {
+    data: *mut u8,
+    width: i32,
+    height: i32,
+    stride: usize,
+    format: rq_pixel_format,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub enum rq_blend_mode {
@@ -284,15 +376,60 @@ pub struct rq_stroke_style {
     dash_offset: f32,
 }
 
-impl From<&rq_stroke_style> for StrokeStyle {
-    fn from(value: &rq_stroke_style) -> Self {
-        StrokeStyle {
-            width: value.width,
-            cap: value.cap.into(),
-            join: value.join.into(),
-            miter_limit: value.miter_limit,
-            dash_array: Vec::new(),
-            dash_offset: 0.0,
+impl rq_stroke_style {
+    // Copies the caller's dash pattern, rejecting negative or non-finite entries and
+    // patterns whose entries are all zero.
+    unsafe fn dash_array(&self) -> Result<Vec<f32>, Error> {
+        if self.dash_array_length == 0 {
+            return Ok(Vec::new());
+        }
+        if self.dash_array.is_null() {
+            return Err(Error::null_pointer("stroke_style.dash_array"));
+        }
+
+        let dashes = std::slice::from_raw_parts(self.dash_array, self.dash_array_length);
+        if !dashes.iter().all(|d| d.is_finite() && *d >= 0.0) {
+            return Err(Error::invalid_argument(
+                "dash array entries must be finite and non-negative",
+            ));
+        }
+        if !dashes.iter().any(|d| *d > 0.0) {
+            return Err(Error::invalid_argument("dash array entries must not all be zero"));
+        }
+        Ok(dashes.to_vec())
+    }
+
+    unsafe fn to_stroke_style(&self) -> Result<StrokeStyle, Error> {
+        if !(self.width.is_finite() && self.width >= 0.0) {
+            return Err(Error::invalid_argument("stroke width must be finite and non-negative"));
+        }
+        if !self.dash_offset.is_finite() {
+            return Err(Error::invalid_argument("dash offset must be finite"));
+        }
+
+        Ok(StrokeStyle {
+            width: self.width,
+            cap: self.cap.into(),
+            join: self.join.into(),
+            miter_limit: self.miter_limit,
+            dash_array: self.dash_array()?,
+            dash_offset: self.dash_offset,
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub enum rq_antialias_mode {
+    None,
+    Gray,
+}
+
+impl From<rq_antialias_mode> for AntialiasMode {
+    fn from(value: rq_antialias_mode) -> Self {
+        match value {
+            rq_antialias_mode::None => AntialiasMode::None,
+            rq_antialias_mode::Gray => AntialiasMode::Gray,
         }
     }
 }
@@ -301,6 +438,7 @@ impl From<&rq_stroke_style> for StrokeStyle {
 pub struct rq_draw_options {
     alpha: f32,
     blend_mode: rq_blend_mode,
+    antialias: rq_antialias_mode,
 }
 
 impl From<&rq_draw_options> for DrawOptions {
@@ -308,21 +446,131 @@ impl From<&rq_draw_options> for DrawOptions {
         DrawOptions {
             alpha: value.alpha,
             blend_mode: value.blend_mode.into(),
-            antialias: raqote::AntialiasMode::Gray,
+            antialias: value.antialias.into(),
+        }
+    }
+}
+
+pub struct rq_path_builder {
+    builder: PathBuilder,
+    // The current point and the start of the current subpath, for relative commands, arcs
+    // and continuing after close.
+    current_pos: Option<Point>,
+    start_pos: Option<Point>,
+    // Set by close, so the next segment starts a new subpath at the current point.
+    needs_move: bool,
+    // Control points of the previous segment, reflected by the smooth curve commands.
+    last_cubic_ctrl: Option<Point>,
+    last_quad_ctrl: Option<Point>,
+}
+// A path, and a copy of it with the other fill rule made the first time it is filled with
+// that rule, since raqote reads the fill rule from the path itself.
+pub struct rq_path(Path, OnceLock<Path>);
+
+impl rq_path {
+    fn new(path: Path) -> Self {
+        Self(path, OnceLock::new())
+    }
+
+    fn with_winding(&self, winding: Winding) -> &Path {
+        if self.0.winding == winding {
+            return &self.0;
+        }
+        self.1.get_or_init(|| Path {
+            ops: self.0.ops.clone(),
+            winding,
+        })
+    }
+}
+pub struct rq_draw_target {
+    target: DrawTarget<PixelBuffer>,
+    stack: Vec<StackEntry>,
+}
+
+// Pixel storage for a draw target, either allocated here or provided by the caller.
+enum PixelBuffer {
+    Owned(Vec<u32>),
+    Borrowed { data: *mut u32, len: usize },
+}
+
+impl AsRef<[u32]> for PixelBuffer {
+    fn as_ref(&self) -> &[u32] {
+        match self {
+            PixelBuffer::Owned(buffer) => buffer,
+            PixelBuffer::Borrowed { data, len } => unsafe {
+                std::slice::from_raw_parts(*data, *len)
+            },
+        }
+    }
+}
+
+impl AsMut<[u32]> for PixelBuffer {
+    fn as_mut(&mut self) -> &mut [u32] {
+        match self {
+            PixelBuffer::Owned(buffer) => buffer,
+            PixelBuffer::Borrowed { data, len } => unsafe {
+                std::slice::from_raw_parts_mut(*data, *len)
+            },
         }
     }
 }
 
-pub struct rq_path_builder(PathBuilder);
-pub struct rq_path(Path);
-pub struct rq_draw_target(DrawTarget);
+// What has been pushed onto a draw target, so clips and layers are popped in the order
+// they were pushed, restore knows what to unwind and recover can rebuild them.
+enum StackEntry {
+    Clip(Clip),
+    Layer { opacity: f32, blend: BlendMode },
+    Save(Transform),
+}
+
+enum Clip {
+    Rect(IntRect),
+    Path(Path, Transform),
+}
+
+impl rq_draw_target {
+    // Rebuilds the raqote draw target after a panic, which may have left it half way
+    // through an operation. Pixels and clips are kept and the transform is reset to
+    // `transform`. Layers are pushed again empty, so anything drawn into them before the
+    // panic is lost. If replaying the clips and layers panics as well, they are dropped
+    // and the stack is emptied to match, but the pixels are still kept.
+    fn recover(&mut self, transform: Transform) {
+        let (width, height) = (self.target.width(), self.target.height());
+        let empty = DrawTarget::from_backing(0, 0, PixelBuffer::Owned(Vec::new()));
+        let buf = std::mem::replace(&mut self.target, empty).into_inner();
+
+        let mut target = DrawTarget::from_backing(width, height, buf);
+        let stack = &self.stack;
+        let replayed = panic::catch_unwind(AssertUnwindSafe(|| {
+            for entry in stack {
+                match entry {
+                    StackEntry::Clip(Clip::Rect(rect)) => target.push_clip_rect(*rect),
+                    StackEntry::Clip(Clip::Path(path, transform)) => {
+                        target.set_transform(transform);
+                        target.push_clip(path);
+                    }
+                    StackEntry::Layer { opacity, blend } => {
+                        target.push_layer_with_blend(*opacity, *blend)
+                    }
+                    StackEntry::Save(_) => {}
+                }
+            }
+        }));
+        if replayed.is_err() {
+            target = DrawTarget::from_backing(width, height, target.into_inner());
+            self.stack.clear();
+        }
+        target.set_transform(&transform);
+        self.target = target;
+    }
+}
 pub struct rq_argb(Vec<u8>);
 pub struct rq_linear_gradient {
     x0: f32,
     y0: f32,
     x1: f32,
     y1: f32,
-    stops: Vec<GradientStop>,
+    stops: GradientStops,
     spread: Spread,
 }
 pub struct rq_radial_gradient {
@@ -332,7 +580,7 @@ pub struct rq_radial_gradient {
     x1: f32,
     y1: f32,
     r1: f32,
-    stops: Vec<GradientStop>,
+    stops: GradientStops,
     spread: Spread,
 }
 pub struct rq_sweep_gradient {
@@ -340,21 +588,53 @@ pub struct rq_sweep_gradient {
     center_y: f32,
     start_angle: f32,
     end_angle: f32,
-    stops: Vec<GradientStop>,
+    stops: GradientStops,
     spread: Spread,
 }
+
+// Gradient stops can still be added after the gradient has been shared with other
+// references, so they live behind a lock rather than relying on unique access.
+struct GradientStops(RwLock<Vec<GradientStop>>);
+
+impl GradientStops {
+    fn new() -> Self {
+        Self(RwLock::new(Vec::new()))
+    }
+
+    fn push(&self, stop: GradientStop) {
+        self.0.write().unwrap().push(stop);
+    }
+
+    fn to_gradient(&self) -> Gradient {
+        Gradient {
+            stops: self.0.read().unwrap().clone(),
+        }
+    }
+}
+
 pub struct rq_image {
     width: i32,
     height: i32,
     data: Vec<u32>,
 }
+impl rq_image {
+    fn as_image(&self) -> Image<'_> {
+        Image {
+            width: self.width,
+            height: self.height,
+            data: &self.data,
+        }
+    }
+}
 pub struct rq_pattern {
-    image: rq_image,
+    image: Arc<rq_image>,
     extend_mode: ExtendMode,
     filter_mode: FilterMode,
     transform: rq_transform,
 }
 
+/// A paint owns one reference to the gradient, pattern or prepared paint it holds, which
+/// `rq_paint_destroy` releases. Retain the handle first if it is used after the paint.
 #[repr(C)]
 pub enum rq_paint {
     Solid(rq_color),
@@ -362,8 +642,306 @@ pub enum rq_paint {
     RadialGradient(*mut rq_radial_gradient),
     SweepGradient(*mut rq_sweep_gradient),
     Pattern(*mut rq_pattern),
+    Prepared(*mut rq_prepared_paint),
+}
+
+/// An opaque paint created from a color, gradient, pattern or prepared paint. It holds its
+/// own reference to what it was created from and is released with `rq_paint_release`.
+/// It replaces passing the `rq_paint` enum to drawing functions, which is deprecated.
+pub struct rq_paint_t(rq_paint);
+
+impl Drop for rq_paint_t {
+    fn drop(&mut self) {
+        unsafe { release_paint(&self.0) }
+    }
+}
+
+/// A paint whose raqote source has been built once, so drawing with it repeatedly does not
+/// rebuild gradients. It is a snapshot: stops added to a gradient afterwards are not seen.
+pub struct rq_prepared_paint(PreparedSource);
+
+enum PreparedSource {
+    Source(Source<'static>),
+    // Image sources borrow their pixels, so a pattern keeps its image and builds the
+    // source for each draw, which does not allocate.
+    Pattern {
+        image: Arc<rq_image>,
+        extend_mode: ExtendMode,
+        filter_mode: FilterMode,
+        transform: Transform,
+    },
+}
+
+impl rq_prepared_paint {
+    // The one place paints are turned into raqote sources.
+    unsafe fn new(paint: &rq_paint) -> Result<Self, Error> {
+        let source = match *paint {
+            rq_paint::Solid(color) => Source::Solid(color.into()),
+            rq_paint::LinearGradient(gradient) => {
+                let g = deref(gradient, "paint")?;
+                Source::new_linear_gradient(
+                    g.stops.to_gradient(),
+                    Point::new(g.x0, g.y0),
+                    Point::new(g.x1, g.y1),
+                    g.spread,
+                )
+            }
+            rq_paint::RadialGradient(gradient) => {
+                let g = deref(gradient, "paint")?;
+                Source::new_two_circle_radial_gradient(
+                    g.stops.to_gradient(),
+                    Point::new(g.x0, g.y0),
+                    g.r0,
+                    Point::new(g.x1, g.y1),
+                    g.r1,
+                    g.spread,
+                )
+            }
+            rq_paint::SweepGradient(gradient) => {
+                let g = deref(gradient, "paint")?;
+                Source::new_sweep_gradient(
+                    g.stops.to_gradient(),
+                    Point::new(g.center_x, g.center_y),
+                    g.start_angle,
+                    g.end_angle,
+                    g.spread,
+                )
+            }
+            rq_paint::Pattern(pattern) => {
+                let p = deref(pattern, "paint")?;
+                return Ok(Self(PreparedSource::Pattern {
+                    image: p.image.clone(),
+                    extend_mode: p.extend_mode,
+                    filter_mode: p.filter_mode,
+                    transform: p.transform.into(),
+                }));
+            }
+            rq_paint::Prepared(prepared) => {
+                return Ok(match &deref(prepared, "paint")?.0 {
+                    PreparedSource::Source(source) => Self(PreparedSource::Source(source.clone())),
+                    PreparedSource::Pattern { image, extend_mode, filter_mode, transform } => {
+                        Self(PreparedSource::Pattern {
+                            image: image.clone(),
+                            extend_mode: *extend_mode,
+                            filter_mode: *filter_mode,
+                            transform: *transform,
+                        })
+                    }
+                });
+            }
+        };
+        Ok(Self(PreparedSource::Source(source)))
+    }
+
+    fn with_source<R>(&self, f: impl FnOnce(&Source) -> R) -> R {
+        match &self.0 {
+            PreparedSource::Source(source) => f(source),
+            PreparedSource::Pattern { image, extend_mode, filter_mode, transform } => {
+                f(&Source::Image(image.as_image(), *extend_mode, *filter_mode, *transform))
+            }
+        }
+    }
+}
+
+// Error handling
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum rq_status {
+    Ok,
+    NullPointer,
+    InvalidArgument,
+    Overflow,
+    InvalidState,
+    Panic,
+    Io,
+    Encode,
+    Decode,
+    Parse,
+    SingularMatrix,
+    Unsupported,
+}
+
+struct Error {
+    status: rq_status,
+    message: String,
+    // Byte offset into the input that a parse error refers to.
+    offset: Option<usize>,
+}
+
+impl Error {
+    fn new(status: rq_status, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            offset: None,
+        }
+    }
+
+    fn null_pointer(name: &str) -> Self {
+        Self::new(rq_status::NullPointer, format!("`{}` must not be null", name))
+    }
+
+    fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::new(rq_status::InvalidArgument, message)
+    }
+
+    fn overflow(message: impl Into<String>) -> Self {
+        Self::new(rq_status::Overflow, message)
+    }
+
+    fn invalid_state(message: impl Into<String>) -> Self {
+        Self::new(rq_status::InvalidState, message)
+    }
+
+    fn io(error: std::io::Error) -> Self {
+        Self::new(rq_status::Io, error.to_string())
+    }
+
+    fn decode(message: impl Into<String>) -> Self {
+        Self::new(rq_status::Decode, message)
+    }
+
+    fn panic(payload: Box<dyn std::any::Any + Send>) -> Self {
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.as_str()
+        } else {
+            "unknown panic payload"
+        };
+        Self::new(rq_status::Panic, format!("panic: {}", message))
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<(rq_status, CString, Option<usize>)>> =
+        const { RefCell::new(None) };
+}
+
+fn set_last_error(error: Error) -> rq_status {
+    let message = CString::new(error.message.replace('\0', ""))
+        .expect("interior nul bytes were removed");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some((error.status, message, error.offset)));
+    error.status
+}
+
+// Runs the body of an exported function, recording any error as the last error on this
+// thread. Panics are caught here so they never unwind across the C boundary.
+fn ffi_status(f: impl FnOnce() -> Result<(), Error>) -> rq_status {
+    match catch_panic(f) {
+        Ok(()) => rq_status::Ok,
+        Err(error) => set_last_error(error),
+    }
+}
+
+fn ffi_pointer<T>(f: impl FnOnce() -> Result<*mut T, Error>) -> *mut T {
+    ffi_value(std::ptr::null_mut(), f)
+}
+
+fn ffi_value<T>(fallback: T, f: impl FnOnce() -> Result<T, Error>) -> T {
+    match catch_panic(f) {
+        Ok(value) => value,
+        Err(error) => {
+            set_last_error(error);
+            fallback
+        }
+    }
+}
+
+fn catch_panic<T>(f: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| Err(Error::panic(payload)))
+}
+
+// Like ffi_status, but for functions that mutate a draw target. If the body panics the
+// draw target is rebuilt so it stays usable.
+unsafe fn ffi_draw(
+    dt: *mut rq_draw_target,
+    f: impl FnOnce(&mut rq_draw_target) -> Result<(), Error>,
+) -> rq_status {
+    ffi_status(|| {
+        let dt = deref_mut(dt, "dt")?;
+        let transform = *dt.target.get_transform();
+        let result = catch_panic(|| f(dt));
+        if matches!(&result, Err(error) if error.status == rq_status::Panic) {
+            dt.recover(transform);
+        }
+        result
+    })
+}
+
+// Validates the dimensions of a pixel buffer and returns its length in pixels.
+fn pixel_count(what: &str, width: i32, height: i32) -> Result<usize, Error> {
+    if width < 0 || height < 0 {
+        return Err(Error::invalid_argument(format!(
+            "{} size {}x{} must not be negative",
+            what, width, height
+        )));
+    }
+    match width.checked_mul(height) {
+        Some(count) => Ok(count as usize),
+        None => Err(Error::overflow(format!(
+            "{} size {}x{} is too large",
+            what, width, height
+        ))),
+    }
 }
 
+// Borrows a caller's array of `count` elements, which may be null when it is empty.
+unsafe fn array_arg<'a, T>(ptr: *const T, count: usize, name: &str) -> Result<&'a [T], Error> {
+    if count == 0 {
+        return Ok(&[]);
+    }
+    if ptr.is_null() {
+        return Err(Error::null_pointer(name));
+    }
+    Ok(std::slice::from_raw_parts(ptr, count))
+}
+
+unsafe fn deref<'a, T>(ptr: *const T, name: &str) -> Result<&'a T, Error> {
+    ptr.as_ref().ok_or_else(|| Error::null_pointer(name))
+}
+
+unsafe fn deref_mut<'a, T>(ptr: *mut T, name: &str) -> Result<&'a mut T, Error> {
+    ptr.as_mut().ok_or_else(|| Error::null_pointer(name))
+}
+
+/// Returns a UTF-8 description of the most recent error on the calling thread, or null if
+/// no call on this thread has failed. The string stays valid until the next failing call
+/// on the same thread.
+#[no_mangle]
+pub extern "C" fn rq_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| match &*last.borrow() {
+        Some((_, message, _)) => message.as_ptr(),
+        None => std::ptr::null(),
+    })
+}
+
+/// Returns the status of the most recent error on the calling thread, or `Ok` if no call on
+/// this thread has failed. Useful after a function that reports failure by returning null.
+#[no_mangle]
+pub extern "C" fn rq_last_error_status() -> rq_status {
+    LAST_ERROR.with(|last| match &*last.borrow() {
+        Some((status, _, _)) => *status,
+        None => rq_status::Ok,
+    })
+}
+
+/// Returns whether the most recent error on the calling thread refers to a position in
+/// its input, such as a `Parse` error from `rq_path_from_svg`, and if so stores the byte
+/// offset in `offset` when it is not null.
+#[no_mangle]
+pub unsafe extern "C" fn rq_last_error_offset(offset: *mut usize) -> bool {
+    let last = LAST_ERROR.with(|last| last.borrow().as_ref().and_then(|(_, _, offset)| *offset));
+    match last {
+        Some(value) => {
+            if let Some(offset) = offset.as_mut() {
+                *offset = value;
+            }
+            true
+        }
+        None => false,
+    }
+}
 
 // Transform functions
 #[no_mangle]
@@ -393,25 +971,145 @@ pub extern "C" fn rq_transform_multiply(a: rq_transform, b: rq_transform) -> rq_
     (ta.then(&tb)).into()
 }
 
+/// Skews by `angle_x` radians along the x axis and `angle_y` radians along the y axis.
+#[no_mangle]
+pub extern "C" fn rq_transform_skew(angle_x: f32, angle_y: f32) -> rq_transform {
+    Transform::new(1.0, angle_y.tan(), angle_x.tan(), 1.0, 0.0, 0.0).into()
+}
+
+/// Rotates by `angle` radians around `cx`, `cy`.
+#[no_mangle]
+pub extern "C" fn rq_transform_rotate_about(angle: f32, cx: f32, cy: f32) -> rq_transform {
+    Transform::translation(-cx, -cy)
+        .then_rotate(euclid::Angle::radians(angle))
+        .then_translate(euclid::vec2(cx, cy))
+        .into()
+}
+
+#[no_mangle]
+pub extern "C" fn rq_transform_determinant(t: rq_transform) -> f32 {
+    Transform::from(t).determinant()
+}
+
+/// Writes the inverse of `t`, failing with `SingularMatrix` if it has none.
+#[no_mangle]
+pub unsafe extern "C" fn rq_transform_invert(
+    t: rq_transform,
+    out_transform: *mut rq_transform,
+) -> rq_status {
+    ffi_status(|| {
+        let out_transform = deref_mut(out_transform, "out_transform")?;
+        *out_transform = invert(&t.into())?.into();
+        Ok(())
+    })
+}
+
+/// Maps a point, including the translation.
+#[no_mangle]
+pub extern "C" fn rq_transform_point(t: rq_transform, point: rq_point) -> rq_point {
+    Transform::from(t).transform_point(Point::new(point.x, point.y)).into()
+}
+
+/// Maps a vector, such as a distance or direction, ignoring the translation.
+#[no_mangle]
+pub extern "C" fn rq_transform_vector(t: rq_transform, vector: rq_point) -> rq_point {
+    let v = Transform::from(t).transform_vector(euclid::vec2(vector.x, vector.y));
+    rq_point { x: v.x, y: v.y }
+}
+
+/// Returns the smallest axis-aligned rect containing the mapped corners of `rect`.
+#[no_mangle]
+pub extern "C" fn rq_transform_rect_bounds(t: rq_transform, rect: rq_rect) -> rq_rect {
+    let t = Transform::from(t);
+    let corners = [
+        (rect.x, rect.y),
+        (rect.x + rect.width, rect.y),
+        (rect.x, rect.y + rect.height),
+        (rect.x + rect.width, rect.y + rect.height),
+    ]
+    .map(|(x, y)| t.transform_point(Point::new(x, y)));
+
+    let (mut min, mut max) = (corners[0], corners[0]);
+    for p in &corners[1..] {
+        min = min.min(*p);
+        max = max.max(*p);
+    }
+    rq_rect { x: min.x, y: min.y, width: max.x - min.x, height: max.y - min.y }
+}
+
+/// Writes the transform that maps the three points of `src` onto the three points of
+/// `dst`. Fails with `SingularMatrix` if the `src` points are collinear.
+#[no_mangle]
+pub unsafe extern "C" fn rq_transform_from_triangles(
+    src: *const rq_point,
+    dst: *const rq_point,
+    out_transform: *mut rq_transform,
+) -> rq_status {
+    ffi_status(|| {
+        if src.is_null() {
+            return Err(Error::null_pointer("src"));
+        }
+        if dst.is_null() {
+            return Err(Error::null_pointer("dst"));
+        }
+        let out_transform = deref_mut(out_transform, "out_transform")?;
+
+        // Map the unit triangle onto each triangle; going back from src through the unit
+        // triangle and on to dst is the mapping between them.
+        let from_unit = |points: *const rq_point| {
+            let [p0, p1, p2] = [0, 1, 2].map(|i| *points.add(i));
+            Transform::new(p1.x - p0.x, p1.y - p0.y, p2.x - p0.x, p2.y - p0.y, p0.x, p0.y)
+        };
+        *out_transform = invert(&from_unit(src))?.then(&from_unit(dst)).into();
+        Ok(())
+    })
+}
+
+// Inverts a transform, treating one whose inverse overflows as singular too.
+fn invert(t: &Transform) -> Result<Transform, Error> {
+    match t.inverse() {
+        Some(inverse) if inverse.to_array().iter().all(|v| v.is_finite()) => Ok(inverse),
+        _ => Err(Error::new(rq_status::SingularMatrix, "transform is not invertible")),
+    }
+}
+
 // Path builder functions
 #[no_mangle]
 pub unsafe extern "C" fn rq_path_builder_create() -> *mut rq_path_builder {
-    Box::into_raw(Box::new(rq_path_builder(PathBuilder::new())))
+    ffi_pointer(|| {
+        Ok(Box::into_raw(Box::new(rq_path_builder::new())))
+    })
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn rq_path_builder_destroy(builder: *mut rq_path_builder) {
-    let _ = Box::from_raw(builder);
+    if !builder.is_null() {
+        let _ = Box::from_raw(builder);
+    }
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn rq_path_builder_move_to(builder: *mut rq_path_builder, x: f32, y: f32) {
-    (*builder).0.move_to(x, y);
+pub unsafe extern "C" fn rq_path_builder_move_to(
+    builder: *mut rq_path_builder,
+    x: f32,
+    y: f32,
+) -> rq_status {
+    ffi_status(|| {
+        deref_mut(builder, "builder")?.move_to(x, y);
+        Ok(())
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn rq_path_builder_line_to(builder: *mut rq_path_builder, x: f32, y: f32) {
-    (*builder).0.line_to(x, y);
+pub unsafe extern "C" fn rq_path_builder_line_to(
+    builder: *mut rq_path_builder,
+    x: f32,
+    y: f32,
+) -> rq_status {
+    ffi_status(|| {
+        deref_mut(builder, "builder")?.line_to(x, y);
+        Ok(())
+    })
 }
 
 #[no_mangle]
@@ -421,8 +1119,11 @@ pub unsafe extern "C" fn rq_path_builder_quad_to(
     cy: f32,
     x: f32,
     y: f32,
-) {
-    (*builder).0.quad_to(cx, cy, x, y);
+) -> rq_status {
+    ffi_status(|| {
+        deref_mut(builder, "builder")?.quad_to(cx, cy, x, y);
+        Ok(())
+    })
 }
 
 #[no_mangle]
@@ -434,13 +1135,19 @@ pub unsafe extern "C" fn rq_path_builder_cubic_to(
     cy2: f32,
     x: f32,
     y: f32,
-) {
-    (*builder).0.cubic_to(cx1, cy1, cx2, cy2, x, y);
+) -> rq_status {
+    ffi_status(|| {
+        deref_mut(builder, "builder")?.cubic_to(cx1, cy1, cx2, cy2, x, y);
+        Ok(())
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn rq_path_builder_close(builder: *mut rq_path_builder) {
-    (*builder).0.close();
+pub unsafe extern "C" fn rq_path_builder_close(builder: *mut rq_path_builder) -> rq_status {
+    ffi_status(|| {
+        deref_mut(builder, "builder")?.close();
+        Ok(())
+    })
 }
 
 #[no_mangle]
@@ -451,640 +1158,3848 @@ pub unsafe extern "C" fn rq_path_builder_arc(
     radius: f32,
     start_angle: f32,
     sweep_angle: f32,
-) {
-    (*builder).0.arc(x, y, radius, start_angle, sweep_angle);
+) -> rq_status {
+    ffi_status(|| {
+        deref_mut(builder, "builder")?.arc(x, y, radius, start_angle, sweep_angle);
+        Ok(())
+    })
 }
 
+/// Adds an SVG elliptical arc from the current point to `x`, `y`, with the x axis of the
+/// ellipse rotated by `x_axis_rotation` degrees. Radii that are too small are scaled up
+/// as in SVG, and a zero radius draws a line.
 #[no_mangle]
-pub unsafe extern "C" fn rq_path_builder_finish(builder: *mut rq_path_builder) -> *mut rq_path {
-    let builder = Box::from_raw(builder);
-    let path = builder.0.finish();
-    Box::into_raw(Box::new(rq_path(path)))
+pub unsafe extern "C" fn rq_path_builder_arc_to(
+    builder: *mut rq_path_builder,
+    rx: f32,
+    ry: f32,
+    x_axis_rotation: f32,
+    large_arc: bool,
+    sweep: bool,
+    x: f32,
+    y: f32,
+) -> rq_status {
+    ffi_status(|| {
+        let builder = deref_mut(builder, "builder")?;
+        builder.arc_to(rx.abs(), ry.abs(), x_axis_rotation, large_arc, sweep, x, y);
+        Ok(())
+    })
 }
 
-// Path functions
+/// Adds a canvas-style arcTo: a line from the current point towards `x1`, `y1`, rounded
+/// into the line towards `x2`, `y2` by a circular arc of the given radius.
 #[no_mangle]
-pub unsafe extern "C" fn rq_path_destroy(path: *mut rq_path) {
-    let _ = Box::from_raw(path);
+pub unsafe extern "C" fn rq_path_builder_arc_to_points(
+    builder: *mut rq_path_builder,
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+    radius: f32,
+) -> rq_status {
+    ffi_status(|| {
+        let builder = deref_mut(builder, "builder")?;
+        if !(radius.is_finite() && radius >= 0.0) {
+            return Err(Error::invalid_argument("radius must be finite and non-negative"));
+        }
+        builder.arc_to_points(x1, y1, x2, y2, radius);
+        Ok(())
+    })
 }
 
+/// Like rq_path_builder_move_to, with coordinates relative to the current point.
 #[no_mangle]
-pub unsafe extern "C" fn rq_rounded_rect(rect: rq_rect, rx: f32, ry: f32) -> *mut rq_path {
-    let (x, y, width, height) = (rect.x, rect.y, rect.width, rect.height);
-
-    let rx = rx.min(width / 2.0);
-    let ry = ry.min(height / 2.0);
-
-    let mut builder = PathBuilderTracker::new();
-
-    builder.move_to(x + rx, y);
-
-    builder.line_to(x + width - rx, y);
-    builder.arc_to(rx, ry, 0.0, false, true, x + width, y + ry);
-
-    builder.line_to(x + width, y + height - ry);
-    builder.arc_to(rx, ry, 0.0, false, true, x + width - rx, y + height);
+pub unsafe extern "C" fn rq_path_builder_rel_move_to(
+    builder: *mut rq_path_builder,
+    dx: f32,
+    dy: f32,
+) -> rq_status {
+    ffi_status(|| {
+        let builder = deref_mut(builder, "builder")?;
+        let p = builder.current_or_origin();
+        builder.move_to(p.x + dx, p.y + dy);
+        Ok(())
+    })
+}
 
-    builder.line_to(x + rx, y + height);
-    builder.arc_to(rx, ry, 0.0, false, true, x, y + height - ry);
+/// Like rq_path_builder_line_to, with coordinates relative to the current point.
+#[no_mangle]
+pub unsafe extern "C" fn rq_path_builder_rel_line_to(
+    builder: *mut rq_path_builder,
+    dx: f32,
+    dy: f32,
+) -> rq_status {
+    ffi_status(|| {
+        let builder = deref_mut(builder, "builder")?;
+        let p = builder.current_or_origin();
+        builder.line_to(p.x + dx, p.y + dy);
+        Ok(())
+    })
+}
 
-    builder.line_to(x, y + ry);
-    builder.arc_to(rx, ry, 0.0, false, true, x + rx, y);
-    
-    builder.close();
+/// Like rq_path_builder_cubic_to, with coordinates relative to the current point.
+#[no_mangle]
+pub unsafe extern "C" fn rq_path_builder_rel_cubic_to(
+    builder: *mut rq_path_builder,
+    dcx1: f32,
+    dcy1: f32,
+    dcx2: f32,
+    dcy2: f32,
+    dx: f32,
+    dy: f32,
+) -> rq_status {
+    ffi_status(|| {
+        let builder = deref_mut(builder, "builder")?;
+        let p = builder.current_or_origin();
+        builder.cubic_to(p.x + dcx1, p.y + dcy1, p.x + dcx2, p.y + dcy2, p.x + dx, p.y + dy);
+        Ok(())
+    })
+}
 
-    Box::into_raw(Box::new(rq_path(builder.finish())))
+/// Adds a quadratic curve whose control point is the previous quadratic control point
+/// reflected about the current point, like the SVG T command.
+#[no_mangle]
+pub unsafe extern "C" fn rq_path_builder_smooth_quad_to(
+    builder: *mut rq_path_builder,
+    x: f32,
+    y: f32,
+) -> rq_status {
+    ffi_status(|| {
+        deref_mut(builder, "builder")?.smooth_quad_to(x, y);
+        Ok(())
+    })
 }
 
-// Helper struct to track position and provide arc_to functionality
-struct PathBuilderTracker {
-    builder: PathBuilder,
-    current_pos: Option<Point>,
+/// Adds a cubic curve whose first control point is the previous cubic's second control
+/// point reflected about the current point, like the SVG S command.
+#[no_mangle]
+pub unsafe extern "C" fn rq_path_builder_smooth_cubic_to(
+    builder: *mut rq_path_builder,
+    cx2: f32,
+    cy2: f32,
+    x: f32,
+    y: f32,
+) -> rq_status {
+    ffi_status(|| {
+        deref_mut(builder, "builder")?.smooth_cubic_to(cx2, cy2, x, y);
+        Ok(())
+    })
 }
 
-impl PathBuilderTracker {
-    fn new() -> Self {
-        Self {
-            builder: PathBuilder::new(),
-            current_pos: None,
-        }
-    }
-    
-    fn move_to(&mut self, x: f32, y: f32) {
-        self.builder.move_to(x, y);
-        self.current_pos = Some(Point::new(x, y));
-    }
-    
-    fn line_to(&mut self, x: f32, y: f32) {
-        self.builder.line_to(x, y);
-        self.current_pos = Some(Point::new(x, y));
-    }
-    
-    fn arc_to(&mut self, rx: f32, ry: f32, x_axis_rotation: f32, large_arc: bool, sweep: bool, x: f32, y: f32) {
-        if let Some(from) = self.current_pos {
-            let svg_arc = kurbo::SvgArc {
-                from: kurbo::Point::new(from.x as f64, from.y as f64),
-                to: kurbo::Point::new(x as f64, y as f64),
-                radii: kurbo::Vec2::new(rx as f64, ry as f64),
-                x_rotation: (x_axis_rotation as f64).to_radians(),
-                large_arc,
-                sweep,
-            };
-
-            match kurbo::Arc::from_svg_arc(&svg_arc) {
-                Some(arc) => {
-                    arc.to_cubic_beziers(0.1, |p1, p2, p| {
-                        self.builder.cubic_to(
-                            p1.x as f32,
-                            p1.y as f32,
-                            p2.x as f32,
-                            p2.y as f32,
-                            p.x as f32,
-                            p.y as f32,
-                        );
-                    });
-                }
-                None => {
-                    self.builder.line_to(x, y);
-                }
+/// Writes the current point to `out_point` and returns true, or returns false if nothing
+/// has been added to the builder yet. After close the current point is the start of the
+/// closed subpath.
+#[no_mangle]
+pub unsafe extern "C" fn rq_path_builder_current_point(
+    builder: *const rq_path_builder,
+    out_point: *mut rq_point,
+) -> bool {
+    ffi_value(false, || {
+        let builder = deref(builder, "builder")?;
+        let out_point = deref_mut(out_point, "out_point")?;
+        Ok(match builder.current_pos {
+            Some(p) => {
+                *out_point = rq_point { x: p.x, y: p.y };
+                true
             }
-            self.current_pos = Some(Point::new(x, y));
-        }
-    }
-    
-    fn close(&mut self) {
-        self.builder.close();
-    }
-    
-    fn finish(self) -> Path {
-        self.builder.finish()
-    }
+            None => false,
+        })
+    })
 }
 
-// Draw target functions
+/// Adds an ellipse as a new closed subpath.
 #[no_mangle]
-pub unsafe extern "C" fn rq_draw_target_create(width: i32, height: i32) -> *mut rq_draw_target {
-    let dt = DrawTarget::new(width, height);
-    Box::into_raw(Box::new(rq_draw_target(dt)))
+pub unsafe extern "C" fn rq_path_builder_add_ellipse(
+    builder: *mut rq_path_builder,
+    cx: f32,
+    cy: f32,
+    rx: f32,
+    ry: f32,
+) -> rq_status {
+    ffi_status(|| add_checked_ellipse(deref_mut(builder, "builder")?, cx, cy, rx, ry))
 }
 
+/// Adds a circle as a new closed subpath.
 #[no_mangle]
-pub unsafe extern "C" fn rq_draw_target_destroy(dt: *mut rq_draw_target) {
-    let _ = Box::from_raw(dt);
+pub unsafe extern "C" fn rq_path_builder_add_circle(
+    builder: *mut rq_path_builder,
+    cx: f32,
+    cy: f32,
+    radius: f32,
+) -> rq_status {
+    rq_path_builder_add_ellipse(builder, cx, cy, radius, radius)
 }
 
+/// Adds a polygon, or a polyline if `closed` is false, as a new subpath.
 #[no_mangle]
-pub unsafe extern "C" fn rq_draw_target_clear(dt: *mut rq_draw_target, color: rq_color) {
-    let solid_color: SolidSource = color.into();
-    (*dt).0.clear(solid_color);
+pub unsafe extern "C" fn rq_path_builder_add_polygon(
+    builder: *mut rq_path_builder,
+    points: *const rq_point,
+    count: usize,
+    closed: bool,
+) -> rq_status {
+    ffi_status(|| {
+        let builder = deref_mut(builder, "builder")?;
+        builder.add_polygon(&polygon_points(points, count)?, closed);
+        Ok(())
+    })
 }
 
+/// Adds a rectangle with per-corner radii as a new closed subpath. See
+/// rq_path_rounded_rect_corners.
 #[no_mangle]
-pub unsafe extern "C" fn rq_draw_target_set_transform(dt: *mut rq_draw_target, transform: rq_transform) {
-    (*dt).0.set_transform(&transform.into());
+pub unsafe extern "C" fn rq_path_builder_add_rounded_rect_corners(
+    builder: *mut rq_path_builder,
+    rect: rq_rect,
+    radii: *const rq_point,
+) -> rq_status {
+    ffi_status(|| {
+        let builder = deref_mut(builder, "builder")?;
+        builder.add_rounded_rect(rect, corner_radii(rect, radii)?);
+        Ok(())
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn rq_draw_target_get_transform(dt: *const rq_draw_target, out_transform: *mut rq_transform) {
-    *out_transform = (*(*dt).0.get_transform()).into();
+pub unsafe extern "C" fn rq_path_builder_finish(builder: *mut rq_path_builder) -> *mut rq_path {
+    ffi_pointer(|| {
+        if builder.is_null() {
+            return Err(Error::null_pointer("builder"));
+        }
+        let builder = Box::from_raw(builder);
+        let path = builder.finish();
+        Ok(Box::into_raw(Box::new(rq_path::new(path))))
+    })
 }
 
-// Drawing functions
+/// Creates a builder that starts with a copy of a path's ops and fill rule, so more
+/// segments can be added to it.
 #[no_mangle]
-pub unsafe extern "C" fn rq_draw_target_fill_path(
-    dt: *mut rq_draw_target,
-    path: *const rq_path,
-    paint: rq_paint,
-    fill_rule: rq_fill_rule,
-    options: *const rq_draw_options,
-) {
-    let draw_options = if options.is_null() {
-        DrawOptions::new()
-    } else {
-        (&*options).into()
-    };
-    
-    let mut path_with_winding = (*path).0.clone();
-    path_with_winding.winding = fill_rule.into();
-    
-    match paint {
-        rq_paint::Solid(color) => {
-            let source = Source::Solid(color.into());
-            (*dt).0.fill(&path_with_winding, &source, &draw_options);
-        },
-        rq_paint::LinearGradient(gradient) => {
-            let g = &*gradient;
-            let start = Point::new(g.x0, g.y0);
-            let end = Point::new(g.x1, g.y1);
-            let gradient_data = Gradient {
-                stops: g.stops.clone(),
-            };
-            let source = Source::new_linear_gradient(gradient_data, start, end, g.spread);
-            (*dt).0.fill(&path_with_winding, &source, &draw_options);
-        },
-        rq_paint::RadialGradient(gradient) => {
-            let g = &*gradient;
-            let gradient_data = Gradient {
-                stops: g.stops.clone(),
-            };
-            let source = Source::new_two_circle_radial_gradient(
-                gradient_data,
-                Point::new(g.x0, g.y0),
-                g.r0,
-                Point::new(g.x1, g.y1),
-                g.r1,
-                g.spread,
-            );
-            (*dt).0.fill(&path_with_winding, &source, &draw_options);
-        },
-        rq_paint::SweepGradient(gradient) => {
-            let g = &*gradient;
-            let gradient_data = Gradient {
-                stops: g.stops.clone(),
-            };
-            let source = Source::new_sweep_gradient(
-                gradient_data,
-                Point::new(g.center_x, g.center_y),
-                g.start_angle,
-                g.end_angle,
-                g.spread,
-            );
-            (*dt).0.fill(&path_with_winding, &source, &draw_options);
-        },
-        rq_paint::Pattern(pattern) => {
-            let p = &*pattern;
-            let raqote_image = Image {
-                width: p.image.width,
-                height: p.image.height,
-                data: &p.image.data,
-            };
-            let source = Source::Image(raqote_image, p.extend_mode, p.filter_mode, p.transform.into());
-            (*dt).0.fill(&path_with_winding, &source, &draw_options);
-        },
-    }
+pub unsafe extern "C" fn rq_path_builder_from_path(path: *const rq_path) -> *mut rq_path_builder {
+    ffi_pointer(|| {
+        let path = deref(path, "path")?;
+        Ok(Box::into_raw(Box::new(rq_path_builder::from_path(&path.0))))
+    })
 }
 
+/// Appends the ops of a path, mapped through `transform`, to a builder. The path's
+/// subpaths are added as they are; the builder keeps its own fill rule.
 #[no_mangle]
-pub unsafe extern "C" fn rq_draw_target_stroke_path(
-    dt: *mut rq_draw_target,
+pub unsafe extern "C" fn rq_path_builder_append_path(
+    builder: *mut rq_path_builder,
     path: *const rq_path,
-    paint: rq_paint,
-    stroke_style: *const rq_stroke_style,
-    options: *const rq_draw_options,
-) {
-    let draw_options = if options.is_null() {
-        DrawOptions::new()
-    } else {
-        (&*options).into()
-    };
-    
-    let style: StrokeStyle = (&*stroke_style).into();
-    
-    match paint {
-        rq_paint::Solid(color) => {
-            let source = Source::Solid(color.into());
-            (*dt).0.stroke(&(*path).0, &source, &style, &draw_options);
-        },
-        rq_paint::LinearGradient(gradient) => {
-            let g = &*gradient;
-            let start = Point::new(g.x0, g.y0);
-            let end = Point::new(g.x1, g.y1);
-            let gradient_data = Gradient {
-                stops: g.stops.clone(),
-            };
-            let source = Source::new_linear_gradient(gradient_data, start, end, g.spread);
-            (*dt).0.stroke(&(*path).0, &source, &style, &draw_options);
-        },
-        rq_paint::RadialGradient(gradient) => {
-            let g = &*gradient;
-            let gradient_data = Gradient {
-                stops: g.stops.clone(),
-            };
-            let source = Source::new_two_circle_radial_gradient(
-                gradient_data,
-                Point::new(g.x0, g.y0),
-                g.r0,
-                Point::new(g.x1, g.y1),
-                g.r1,
-                g.spread,
-            );
-            (*dt).0.stroke(&(*path).0, &source, &style, &draw_options);
-        },
-        rq_paint::SweepGradient(gradient) => {
-            let g = &*gradient;
-            let gradient_data = Gradient {
-                stops: g.stops.clone(),
-            };
-            let source = Source::new_sweep_gradient(
-                gradient_data,
-                Point::new(g.center_x, g.center_y),
-                g.start_angle,
-                g.end_angle,
-                g.spread,
-            );
-            (*dt).0.stroke(&(*path).0, &source, &style, &draw_options);
-        },
-        rq_paint::Pattern(pattern) => {
-            let p = &*pattern;
-            let raqote_image = Image {
-                width: p.image.width,
-                height: p.image.height,
-                data: &p.image.data,
-            };
-            let source = Source::Image(raqote_image, p.extend_mode, p.filter_mode, p.transform.into());
-            (*dt).0.stroke(&(*path).0, &source, &style, &draw_options);
-        },
-    }
+    transform: rq_transform,
+) -> rq_status {
+    ffi_status(|| {
+        let builder = deref_mut(builder, "builder")?;
+        builder.append_path(&deref(path, "path")?.0, &transform.into());
+        Ok(())
+    })
 }
 
+// Path functions
 #[no_mangle]
-pub unsafe extern "C" fn rq_draw_target_fill_rect(
-    dt: *mut rq_draw_target,
-    rect: rq_rect,
-    paint: rq_paint,
-    options: *const rq_draw_options,
-) {
-    let draw_options = if options.is_null() {
-        DrawOptions::new()
-    } else {
-        (&*options).into()
-    };
-    
-    match paint {
-        rq_paint::Solid(color) => {
-            let source = Source::Solid(color.into());
-            (*dt).0.fill_rect(rect.x, rect.y, rect.width, rect.height, &source, &draw_options);
-        },
-        rq_paint::LinearGradient(gradient) => {
-            let g = &*gradient;
-            let start = Point::new(g.x0, g.y0);
-            let end = Point::new(g.x1, g.y1);
-            let gradient_data = Gradient {
-                stops: g.stops.clone(),
-            };
-            let source = Source::new_linear_gradient(gradient_data, start, end, g.spread);
-            (*dt).0.fill_rect(rect.x, rect.y, rect.width, rect.height, &source, &draw_options);
-        },
-        rq_paint::RadialGradient(gradient) => {
-            let g = &*gradient;
-            let gradient_data = Gradient {
-                stops: g.stops.clone(),
-            };
-            let source = Source::new_two_circle_radial_gradient(
-                gradient_data,
-                Point::new(g.x0, g.y0),
-                g.r0,
-                Point::new(g.x1, g.y1),
-                g.r1,
-                g.spread,
-            );
-            (*dt).0.fill_rect(rect.x, rect.y, rect.width, rect.height, &source, &draw_options);
-        },
-        rq_paint::SweepGradient(gradient) => {
-            let g = &*gradient;
-            let gradient_data = Gradient {
-                stops: g.stops.clone(),
-            };
-            let source = Source::new_sweep_gradient(
-                gradient_data,
-                Point::new(g.center_x, g.center_y),
-                g.start_angle,
-                g.end_angle,
-                g.spread,
-            );
-            (*dt).0.fill_rect(rect.x, rect.y, rect.width, rect.height, &source, &draw_options);
-        },
-        rq_paint::Pattern(pattern) => {
-            let p = &*pattern;
-            let raqote_image = Image {
-                width: p.image.width,
-                height: p.image.height,
-                data: &p.image.data,
-            };
-            let source = Source::Image(raqote_image, p.extend_mode, p.filter_mode, p.transform.into());
-            (*dt).0.fill_rect(rect.x, rect.y, rect.width, rect.height, &source, &draw_options);
-        },
+pub unsafe extern "C" fn rq_path_destroy(path: *mut rq_path) {
+    if !path.is_null() {
+        let _ = Box::from_raw(path);
     }
 }
 
+/// Returns a copy of a path.
 #[no_mangle]
-pub unsafe extern "C" fn rq_draw_target_stroke_rect(
-    dt: *mut rq_draw_target,
-    rect: rq_rect,
-    paint: rq_paint,
-    stroke_style: *const rq_stroke_style,
-    options: *const rq_draw_options,
-) {
-    let draw_options = if options.is_null() {
-        DrawOptions::new()
-    } else {
-        (&*options).into()
-    };
-    
-    // Create a path for the rectangle
-    let mut builder = PathBuilder::new();
-    builder.move_to(rect.x, rect.y);
-    builder.line_to(rect.x + rect.width, rect.y);
-    builder.line_to(rect.x + rect.width, rect.y + rect.height);
-    builder.line_to(rect.x, rect.y + rect.height);
-    builder.close();
-    let path = builder.finish();
-    
-    let style: StrokeStyle = (&*stroke_style).into();
-    
-    match paint {
-        rq_paint::Solid(color) => {
-            let source = Source::Solid(color.into());
-            (*dt).0.stroke(&path, &source, &style, &draw_options);
-        },
-        rq_paint::LinearGradient(gradient) => {
-            let g = &*gradient;
-            let start = Point::new(g.x0, g.y0);
-            let end = Point::new(g.x1, g.y1);
-            let gradient_data = Gradient {
-                stops: g.stops.clone(),
-            };
-            let source = Source::new_linear_gradient(gradient_data, start, end, g.spread);
-            (*dt).0.stroke(&path, &source, &style, &draw_options);
-        },
-        rq_paint::RadialGradient(gradient) => {
-            let g = &*gradient;
-            let gradient_data = Gradient {
-                stops: g.stops.clone(),
-            };
-            let source = Source::new_two_circle_radial_gradient(
-                gradient_data,
-                Point::new(g.x0, g.y0),
-                g.r0,
-                Point::new(g.x1, g.y1),
-                g.r1,
-                g.spread,
-            );
-            (*dt).0.stroke(&path, &source, &style, &draw_options);
-        },
-        rq_paint::SweepGradient(gradient) => {
-            let g = &*gradient;
-            let gradient_data = Gradient {
-                stops: g.stops.clone(),
-            };
-            let source = Source::new_sweep_gradient(
-                gradient_data,
-                Point::new(g.center_x, g.center_y),
-                g.start_angle,
-                g.end_angle,
-                g.spread,
-            );
-            (*dt).0.stroke(&path, &source, &style, &draw_options);
-        },
-        rq_paint::Pattern(pattern) => {
-            let p = &*pattern;
-            let raqote_image = Image {
-                width: p.image.width,
-                height: p.image.height,
-                data: &p.image.data,
-            };
-            let source = Source::Image(raqote_image, p.extend_mode, p.filter_mode, p.transform.into());
-            (*dt).0.stroke(&path, &source, &style, &draw_options);
-        },
-    }
+pub unsafe extern "C" fn rq_path_clone(path: *const rq_path) -> *mut rq_path {
+    ffi_pointer(|| Ok(Box::into_raw(Box::new(rq_path::new(deref(path, "path")?.0.clone())))))
 }
 
-// Pixel data access
+/// Returns a new path with every point of `path` mapped through `transform`.
 #[no_mangle]
-pub unsafe extern "C" fn rq_draw_target_get_data(dt: *mut rq_draw_target) -> *mut rq_argb {
-    let data = (*dt).0.get_data();
-    let mut buffer = Vec::with_capacity(data.len() * 4);
-    
-    // Convert from raqote format to BGRA format expected by blend2d
-    for pixel in data {
-        let a = (pixel >> 24) & 0xff;
-        let r = (pixel >> 16) & 0xff;
-        let g = (pixel >> 8) & 0xff;
-        let b = pixel & 0xff;
-        
-        buffer.push(b as u8);  // B
-        buffer.push(g as u8);  // G  
-        buffer.push(r as u8);  // R
-        buffer.push(a as u8);  // A
-    }
-    
-    Box::into_raw(Box::new(rq_argb(buffer)))
+pub unsafe extern "C" fn rq_path_transform(
+    path: *const rq_path,
+    transform: rq_transform,
+) -> *mut rq_path {
+    ffi_pointer(|| {
+        let path = deref(path, "path")?.0.clone().transform(&transform.into());
+        Ok(Box::into_raw(Box::new(rq_path::new(path))))
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn rq_argb_data(data: *const rq_argb) -> *const u8 {
-    (*data).0.as_ptr()
+pub unsafe extern "C" fn rq_rounded_rect(rect: rq_rect, rx: f32, ry: f32) -> *mut rq_path {
+    ffi_pointer(|| {
+        let rx = rx.min(rect.width / 2.0);
+        let ry = ry.min(rect.height / 2.0);
+
+        let mut builder = rq_path_builder::new();
+        builder.add_rounded_rect(rect, [Point::new(rx, ry); 4]);
+        Ok(Box::into_raw(Box::new(rq_path::new(builder.finish()))))
+    })
 }
 
+/// Returns an ellipse centered on `cx`, `cy` with radii `rx` and `ry`.
 #[no_mangle]
-pub unsafe extern "C" fn rq_argb_destroy(data: *mut rq_argb) {
-    let _ = Box::from_raw(data);
+pub unsafe extern "C" fn rq_path_ellipse(cx: f32, cy: f32, rx: f32, ry: f32) -> *mut rq_path {
+    ffi_pointer(|| {
+        let mut builder = rq_path_builder::new();
+        add_checked_ellipse(&mut builder, cx, cy, rx, ry)?;
+        Ok(Box::into_raw(Box::new(rq_path::new(builder.finish()))))
+    })
 }
 
+/// Returns a circle centered on `cx`, `cy`.
 #[no_mangle]
-pub unsafe extern "C" fn rq_draw_target_width(dt: *const rq_draw_target) -> i32 {
-    (*dt).0.width()
+pub unsafe extern "C" fn rq_path_circle(cx: f32, cy: f32, radius: f32) -> *mut rq_path {
+    rq_path_ellipse(cx, cy, radius, radius)
 }
 
+/// Returns a path through `count` points, closed into a polygon if `closed` is true and
+/// left open as a polyline otherwise.
 #[no_mangle]
-pub unsafe extern "C" fn rq_draw_target_height(dt: *const rq_draw_target) -> i32 {
-    (*dt).0.height()
+pub unsafe extern "C" fn rq_path_polygon(
+    points: *const rq_point,
+    count: usize,
+    closed: bool,
+) -> *mut rq_path {
+    ffi_pointer(|| {
+        let mut builder = rq_path_builder::new();
+        builder.add_polygon(&polygon_points(points, count)?, closed);
+        Ok(Box::into_raw(Box::new(rq_path::new(builder.finish()))))
+    })
 }
 
-// Gradient functions
+/// Returns a rectangle with its own elliptical radii at each corner. `radii` holds four
+/// points whose x and y are the horizontal and vertical radius of the top-left, top-right,
+/// bottom-right and bottom-left corners. Radii that don't fit are scaled down together,
+/// as CSS border-radius does.
 #[no_mangle]
-pub unsafe extern "C" fn rq_linear_gradient_create(
-    x0: f32,
-    y0: f32,
-    x1: f32,
-    y1: f32,
-    spread: rq_spread_mode,
-) -> *mut rq_linear_gradient {
-    Box::into_raw(Box::new(rq_linear_gradient {
-        x0,
-        y0,
-        x1,
-        y1,
-        stops: Vec::new(),
-        spread: spread.into(),
-    }))
+pub unsafe extern "C" fn rq_path_rounded_rect_corners(
+    rect: rq_rect,
+    radii: *const rq_point,
+) -> *mut rq_path {
+    ffi_pointer(|| {
+        let mut builder = rq_path_builder::new();
+        builder.add_rounded_rect(rect, corner_radii(rect, radii)?);
+        Ok(Box::into_raw(Box::new(rq_path::new(builder.finish()))))
+    })
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn rq_radial_gradient_create(
-    x0: f32,
-    y0: f32,
-    r0: f32,
-    x1: f32,
-    y1: f32,
-    r1: f32,
-    spread: rq_spread_mode,
-) -> *mut rq_radial_gradient {
-    Box::into_raw(Box::new(rq_radial_gradient {
-        x0,
-        y0,
-        r0,
-        x1,
-        y1,
-        r1,
-        stops: Vec::new(),
-        spread: spread.into(),
-    }))
+fn add_checked_ellipse(
+    builder: &mut rq_path_builder,
+    cx: f32,
+    cy: f32,
+    rx: f32,
+    ry: f32,
+) -> Result<(), Error> {
+    if !(rx.is_finite() && ry.is_finite() && rx >= 0.0 && ry >= 0.0) {
+        return Err(Error::invalid_argument("radii must be finite and non-negative"));
+    }
+    builder.add_ellipse(cx, cy, rx, ry);
+    Ok(())
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn rq_linear_gradient_add_stop(
-    gradient: *mut rq_linear_gradient,
-    stop: rq_gradient_stop,
-) {
-    (*gradient).stops.push(stop.into());
+unsafe fn polygon_points(points: *const rq_point, count: usize) -> Result<Vec<Point>, Error> {
+    let points = array_arg(points, count, "points")?;
+    Ok(points.iter().map(|p| Point::new(p.x, p.y)).collect())
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn rq_radial_gradient_add_stop(
-    gradient: *mut rq_radial_gradient,
-    stop: rq_gradient_stop,
-) {
-    (*gradient).stops.push(stop.into());
+// Reads the four corner radii and scales them down by a common factor where adjacent
+// radii add up to more than the side they share.
+unsafe fn corner_radii(rect: rq_rect, radii: *const rq_point) -> Result<[Point; 4], Error> {
+    if radii.is_null() {
+        return Err(Error::null_pointer("radii"));
+    }
+    let radii = std::slice::from_raw_parts(radii, 4);
+    if radii.iter().any(|r| !(r.x.is_finite() && r.y.is_finite() && r.x >= 0.0 && r.y >= 0.0)) {
+        return Err(Error::invalid_argument("corner radii must be finite and non-negative"));
+    }
+    let [top_left, top_right, bottom_right, bottom_left] =
+        [0, 1, 2, 3].map(|i| Point::new(radii[i].x, radii[i].y));
+
+    let (width, height) = (rect.width.abs(), rect.height.abs());
+    let mut scale: f32 = 1.0;
+    for (side, sum) in [
+        (width, top_left.x + top_right.x),
+        (width, bottom_left.x + bottom_right.x),
+        (height, top_left.y + bottom_left.y),
+        (height, top_right.y + bottom_right.y),
+    ] {
+        if sum > side {
+            scale = scale.min(side / sum);
+        }
+    }
+    Ok([top_left, top_right, bottom_right, bottom_left].map(|r| r * scale))
 }
 
+/// Writes the tight bounds of a path, computed from the curves themselves rather than
+/// their control points. An empty path has empty bounds at the origin.
 #[no_mangle]
-pub unsafe extern "C" fn rq_linear_gradient_destroy(gradient: *mut rq_linear_gradient) {
-    let _ = Box::from_raw(gradient);
+pub unsafe extern "C" fn rq_path_bounds(
+    path: *const rq_path,
+    out_bounds: *mut rq_rect,
+) -> rq_status {
+    ffi_status(|| {
+        let path = deref(path, "path")?;
+        let out_bounds = deref_mut(out_bounds, "out_bounds")?;
+
+        let bez_path = to_bez_path(&path.0, false);
+        let bounds = match bez_path.elements().is_empty() {
+            true => kurbo::Rect::ZERO,
+            false => kurbo::Shape::bounding_box(&bez_path),
+        };
+        *out_bounds = rq_rect {
+            x: bounds.x0 as f32,
+            y: bounds.y0 as f32,
+            width: bounds.width() as f32,
+            height: bounds.height() as f32,
+        };
+        Ok(())
+    })
 }
 
+/// Writes whether a point is inside the path when filled with the given rule. Open
+/// subpaths are treated as closed, as they are when filling.
 #[no_mangle]
-pub unsafe extern "C" fn rq_radial_gradient_destroy(gradient: *mut rq_radial_gradient) {
-    let _ = Box::from_raw(gradient);
+pub unsafe extern "C" fn rq_path_contains_point(
+    path: *const rq_path,
+    point: rq_point,
+    fill_rule: rq_fill_rule,
+    out_contains: *mut bool,
+) -> rq_status {
+    ffi_status(|| {
+        let path = deref(path, "path")?;
+        let out_contains = deref_mut(out_contains, "out_contains")?;
+
+        let winding = kurbo::Shape::winding(
+            &to_bez_path(&path.0, true),
+            kurbo::Point::new(point.x as f64, point.y as f64),
+        );
+        *out_contains = match fill_rule {
+            rq_fill_rule::Winding => winding != 0,
+            rq_fill_rule::EvenOdd => winding % 2 != 0,
+        };
+        Ok(())
+    })
 }
 
+/// Writes the length of a path, accurate to within `tolerance`.
 #[no_mangle]
-pub unsafe extern "C" fn rq_sweep_gradient_create(
-    center_x: f32,
-    center_y: f32,
-    start_angle: f32,
-    end_angle: f32,
-    spread: rq_spread_mode,
-) -> *mut rq_sweep_gradient {
-    Box::into_raw(Box::new(rq_sweep_gradient {
-        center_x,
-        center_y,
-        start_angle,
-        end_angle,
-        stops: Vec::new(),
-        spread: spread.into(),
-    }))
+pub unsafe extern "C" fn rq_path_length(
+    path: *const rq_path,
+    tolerance: f32,
+    out_length: *mut f32,
+) -> rq_status {
+    ffi_status(|| {
+        let path = deref(path, "path")?;
+        let out_length = deref_mut(out_length, "out_length")?;
+        if !(tolerance.is_finite() && tolerance > 0.0) {
+            return Err(Error::invalid_argument("tolerance must be positive and finite"));
+        }
+
+        let bez_path = to_bez_path(&path.0, false);
+        *out_length = kurbo::Shape::perimeter(&bez_path, tolerance as f64) as f32;
+        Ok(())
+    })
 }
 
+/// Writes the signed area enclosed by a path, treating open subpaths as closed. The area
+/// is positive for subpaths drawn clockwise on screen (with y pointing down).
 #[no_mangle]
-pub unsafe extern "C" fn rq_sweep_gradient_add_stop(
-    gradient: *mut rq_sweep_gradient,
-    stop: rq_gradient_stop,
-) {
-    (*gradient).stops.push(stop.into());
+pub unsafe extern "C" fn rq_path_area(path: *const rq_path, out_area: *mut f32) -> rq_status {
+    ffi_status(|| {
+        let path = deref(path, "path")?;
+        let out_area = deref_mut(out_area, "out_area")?;
+        *out_area = kurbo::Shape::area(&to_bez_path(&path.0, true)) as f32;
+        Ok(())
+    })
 }
 
+/// Returns the filled outline of a path stroked with the given style, including caps,
+/// joins and dashes, as a new path using the nonzero fill rule.
 #[no_mangle]
-pub unsafe extern "C" fn rq_sweep_gradient_destroy(gradient: *mut rq_sweep_gradient) {
-    let _ = Box::from_raw(gradient);
+pub unsafe extern "C" fn rq_path_stroke_to_path(
+    path: *const rq_path,
+    stroke_style: *const rq_stroke_style,
+) -> *mut rq_path {
+    ffi_pointer(|| {
+        let path = deref(path, "path")?;
+        let style = deref(stroke_style, "stroke_style")?.to_stroke_style()?;
+        Ok(Box::into_raw(Box::new(rq_path::new(stroke_outline(&path.0, &style)))))
+    })
 }
 
-// Pattern functions  
+/// Writes whether a point lies on the path when stroked with the given style.
 #[no_mangle]
-pub unsafe extern "C" fn rq_image_create(width: i32, height: i32, data: *const u32) -> *mut rq_image {
-    let size = (width * height) as usize;
-    let data_slice = std::slice::from_raw_parts(data, size);
-    
-    Box::into_raw(Box::new(rq_image {
-        width,
-        height,
-        data: data_slice.to_vec(),
-    }))
+pub unsafe extern "C" fn rq_path_stroke_contains_point(
+    path: *const rq_path,
+    stroke_style: *const rq_stroke_style,
+    point: rq_point,
+    out_contains: *mut bool,
+) -> rq_status {
+    ffi_status(|| {
+        let path = deref(path, "path")?;
+        let style = deref(stroke_style, "stroke_style")?.to_stroke_style()?;
+        let out_contains = deref_mut(out_contains, "out_contains")?;
+
+        let winding = kurbo::Shape::winding(
+            &to_bez_path(&stroke_outline(&path.0, &style), true),
+            kurbo::Point::new(point.x as f64, point.y as f64),
+        );
+        *out_contains = winding != 0;
+        Ok(())
+    })
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn rq_image_destroy(image: *mut rq_image) {
-    let _ = Box::from_raw(image);
+// Strokes a path the way DrawTarget::stroke does with an identity transform: flatten,
+// dash, then run raqote's stroker.
+fn stroke_outline(path: &Path, style: &StrokeStyle) -> Path {
+    let mut path = path.flatten(0.1);
+    if !style.dash_array.is_empty() {
+        path = dash_path(&path, &style.dash_array, style.dash_offset);
+    }
+    raqote::stroke_to_path(&path, style)
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn rq_pattern_create(
-    image: *mut rq_image,
-    extend_mode: rq_extend_mode,
-    filter_mode: rq_filter_mode,
-    transform: rq_transform,
-) -> *mut rq_pattern {
-    Box::into_raw(Box::new(rq_pattern {
-        image: std::ptr::read(image),
-        extend_mode: extend_mode.into(),
-        filter_mode: filter_mode.into(),
-        transform,
-    }))
+// Splits a flattened path into dashes. raqote does not export its dasher, so this follows
+// the same rules: the pattern restarts at every subpath, and on a closed subpath the last
+// dash joins the first one when both touch the start point.
+fn dash_path(path: &Path, dash_array: &[f32], dash_offset: f32) -> Path {
+    let mut period: f32 = dash_array.iter().sum();
+    if dash_array.len() % 2 == 1 {
+        period *= 2.0;
+    }
+    let mut dashed = PathBuilder::new();
+    if period <= 0.0 {
+        return dashed.finish();
+    }
+
+    // Find where in the pattern the offset lands, as (index, remaining length).
+    let mut offset = dash_offset % period;
+    if offset < 0.0 {
+        offset += period;
+    }
+    let mut initial = (0, dash_array[0]);
+    while offset > initial.1 {
+        offset -= initial.1;
+        initial.0 += 1;
+        initial.1 = dash_array[initial.0 % dash_array.len()];
+    }
+    initial.1 -= offset;
+
+    let mut points: Vec<Point> = Vec::new();
+    let mut emit_subpath = |points: &mut Vec<Point>, closed: bool| {
+        if closed {
+            if let Some(&start) = points.first() {
+                points.push(start);
+            }
+        }
+        let (mut index, mut remaining) = initial;
+        let mut dashes: Vec<Vec<Point>> = Vec::new();
+        let mut current = points.first().filter(|_| index % 2 == 0).map(|&p| vec![p]);
+        for segment in points.windows(2) {
+            // Step along the segment the way raqote does, so the splits round identically.
+            let (mut from, to) = (segment[0], segment[1]);
+            let mut length = (to - from).length();
+            let direction = (to - from).normalize();
+            while length > remaining {
+                let split = from + direction * remaining;
+                length -= remaining;
+                from = split;
+                match current.take() {
+                    Some(mut dash) => {
+                        dash.push(split);
+                        dashes.push(dash);
+                    }
+                    None => current = Some(vec![split]),
+                }
+                index += 1;
+                remaining = dash_array[index % dash_array.len()];
+            }
+            if let Some(dash) = &mut current {
+                dash.push(to);
+            }
+            remaining -= length;
+        }
+
+        let mut closed_dash = false;
+        if let Some(mut dash) = current {
+            if closed && dashes.is_empty() && initial.0 % 2 == 0 {
+                closed_dash = true;
+                dashes.push(dash);
+            } else if closed && initial.0 % 2 == 0 {
+                dash.extend_from_slice(&dashes[0][1..]);
+                dashes[0] = dash;
+            } else {
+                dashes.push(dash);
+            }
+        }
+        for dash in &dashes {
+            dashed.move_to(dash[0].x, dash[0].y);
+            for p in &dash[1..] {
+                dashed.line_to(p.x, p.y);
+            }
+            if closed_dash {
+                dashed.close();
+            }
+        }
+        points.clear();
+    };
+
+    for op in &path.ops {
+        match *op {
+            PathOp::MoveTo(p) => {
+                emit_subpath(&mut points, false);
+                points.push(p);
+            }
+            PathOp::LineTo(p) => points.push(p),
+            PathOp::Close => {
+                let start = points.first().copied();
+                emit_subpath(&mut points, true);
+                // Drawing may continue from the start of the closed subpath.
+                points.extend(start);
+            }
+            PathOp::QuadTo(..) | PathOp::CubicTo(..) => unreachable!("path is flattened"),
+        }
+    }
+    emit_subpath(&mut points, false);
+    dashed.finish()
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn rq_pattern_destroy(pattern: *mut rq_pattern) {
-    let _ = Box::from_raw(pattern);
+// Converts a path to kurbo for geometry queries. Area and containment need every subpath
+// closed, as filling does, while bounds and length must not add the closing edge.
+fn to_bez_path(path: &Path, close_subpaths: bool) -> kurbo::BezPath {
+    let point = |p: Point| kurbo::Point::new(p.x as f64, p.y as f64);
+    let mut bez_path = kurbo::BezPath::new();
+    // Where the current subpath started.
+    let mut start = None;
+    let mut open = false;
+    for op in &path.ops {
+        if open && close_subpaths && matches!(op, PathOp::MoveTo(_)) {
+            bez_path.close_path();
+        }
+        // kurbo needs every subpath to begin with a move. Like raqote's rasterizer, a
+        // segment with no current point starts at its first point, and one following a
+        // close starts where the closed subpath did.
+        if !open {
+            if let PathOp::LineTo(p) | PathOp::QuadTo(p, _) | PathOp::CubicTo(p, _, _) = *op {
+                let p = *start.get_or_insert(point(p));
+                bez_path.move_to(p);
+            }
+        }
+        match *op {
+            PathOp::MoveTo(p) => {
+                start = Some(point(p));
+                bez_path.move_to(point(p));
+            }
+            PathOp::LineTo(p) => bez_path.line_to(point(p)),
+            PathOp::QuadTo(c, p) => bez_path.quad_to(point(c), point(p)),
+            PathOp::CubicTo(c1, c2, p) => bez_path.curve_to(point(c1), point(c2), point(p)),
+            // Closing with nothing open draws nothing.
+            PathOp::Close if open => bez_path.close_path(),
+            PathOp::Close => {}
+        }
+        open = !matches!(op, PathOp::Close);
+    }
+    if open && close_subpaths {
+        bez_path.close_path();
+    }
+    bez_path
 }
 
-// Paint helper functions
-#[no_mangle]
-pub unsafe extern "C" fn rq_paint_destroy(paint: rq_paint) {
-    match paint {
-        rq_paint::Solid(_) => {
-            // Nothing to clean up for solid colors
-        },
-        rq_paint::LinearGradient(_) => {
-            // Gradients are managed externally, don't destroy here
-        },
-        rq_paint::RadialGradient(_) => {
-            // Gradients are managed externally, don't destroy here
-        },
-        rq_paint::SweepGradient(_) => {
-            // Gradients are managed externally, don't destroy here
-        },
-        rq_paint::Pattern(_) => {
-            // Patterns are managed externally, don't destroy here
-        },
+impl rq_path_builder {
+    fn new() -> Self {
+        Self {
+            builder: PathBuilder::new(),
+            current_pos: None,
+            start_pos: None,
+            needs_move: false,
+            last_cubic_ctrl: None,
+            last_quad_ctrl: None,
+        }
+    }
+
+    fn from_path(path: &Path) -> Self {
+        let mut builder = Self::new();
+        builder.builder = PathBuilder::from(Path { ops: Vec::new(), winding: path.winding });
+        builder.append_path(path, &Transform::identity());
+        builder
+    }
+
+    fn current_or_origin(&self) -> Point {
+        self.current_pos.unwrap_or_default()
     }
-}
\ No newline at end of file
+
+    // Starts a new subpath at the current point if the last one was closed, and returns
+    // the point the next segment starts from.
+    fn begin_segment(&mut self) -> Point {
+        if let (true, Some(p)) = (self.needs_move, self.current_pos) {
+            self.builder.move_to(p.x, p.y);
+        }
+        self.needs_move = false;
+        self.current_or_origin()
+    }
+
+    fn end_segment(&mut self, to: Point, cubic_ctrl: Option<Point>, quad_ctrl: Option<Point>) {
+        self.current_pos = Some(to);
+        self.last_cubic_ctrl = cubic_ctrl;
+        self.last_quad_ctrl = quad_ctrl;
+    }
+
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.builder.move_to(x, y);
+        self.needs_move = false;
+        self.end_segment(Point::new(x, y), None, None);
+        self.start_pos = self.current_pos;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.begin_segment();
+        self.builder.line_to(x, y);
+        self.end_segment(Point::new(x, y), None, None);
+    }
+
+    fn quad_to(&mut self, cx: f32, cy: f32, x: f32, y: f32) {
+        self.begin_segment();
+        self.builder.quad_to(cx, cy, x, y);
+        self.end_segment(Point::new(x, y), None, Some(Point::new(cx, cy)));
+    }
+
+    fn cubic_to(&mut self, cx1: f32, cy1: f32, cx2: f32, cy2: f32, x: f32, y: f32) {
+        self.begin_segment();
+        self.builder.cubic_to(cx1, cy1, cx2, cy2, x, y);
+        self.end_segment(Point::new(x, y), Some(Point::new(cx2, cy2)), None);
+    }
+
+    // The control point is the previous quadratic control point reflected about the current
+    // point, or the current point itself if the previous segment was not quadratic.
+    fn smooth_quad_to(&mut self, x: f32, y: f32) {
+        let c = reflect(self.last_quad_ctrl, self.current_or_origin());
+        self.quad_to(c.x, c.y, x, y);
+    }
+
+    fn smooth_cubic_to(&mut self, cx2: f32, cy2: f32, x: f32, y: f32) {
+        let c1 = reflect(self.last_cubic_ctrl, self.current_or_origin());
+        self.cubic_to(c1.x, c1.y, cx2, cy2, x, y);
+    }
+
+    // An SVG elliptical arc from the current point to x, y. Without a current point this
+    // only moves to x, y.
+    #[allow(clippy::too_many_arguments)]
+    fn arc_to(&mut self, rx: f32, ry: f32, x_axis_rotation: f32, large_arc: bool, sweep: bool, x: f32, y: f32) {
+        let Some(_) = self.current_pos else {
+            self.move_to(x, y);
+            return;
+        };
+        let from = self.begin_segment();
+        let svg_arc = kurbo::SvgArc {
+            from: kurbo::Point::new(from.x as f64, from.y as f64),
+            to: kurbo::Point::new(x as f64, y as f64),
+            radii: kurbo::Vec2::new(rx as f64, ry as f64),
+            x_rotation: (x_axis_rotation as f64).to_radians(),
+            large_arc,
+            sweep,
+        };
+
+        match kurbo::Arc::from_svg_arc(&svg_arc) {
+            Some(arc) => {
+                arc.to_cubic_beziers(0.1, |p1, p2, p| {
+                    self.builder.cubic_to(
+                        p1.x as f32,
+                        p1.y as f32,
+                        p2.x as f32,
+                        p2.y as f32,
+                        p.x as f32,
+                        p.y as f32,
+                    );
+                });
+            }
+            None => {
+                self.builder.line_to(x, y);
+            }
+        }
+        self.end_segment(Point::new(x, y), None, None);
+    }
+
+    // The canvas arcTo: a line towards x1, y1 that turns towards x2, y2 along a circle of
+    // the given radius touching both lines. Degenerate cases draw a line to x1, y1.
+    fn arc_to_points(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) {
+        let Some(p0) = self.current_pos else {
+            self.move_to(x1, y1);
+            return;
+        };
+        let (p1, p2) = (Point::new(x1, y1), Point::new(x2, y2));
+        let (d0, d2) = (p0 - p1, p2 - p1);
+        let cross = d0.x * d2.y - d0.y * d2.x;
+        let collinear = cross.abs() <= f32::EPSILON * d0.length() * d2.length();
+        if radius == 0.0 || collinear {
+            self.line_to(x1, y1);
+            return;
+        }
+
+        // The tangent points are where a circle of the given radius in the corner touches
+        // both lines, at the same distance from the corner.
+        let (u0, u2) = (d0.normalize(), d2.normalize());
+        let half_angle = (u0.dot(u2).clamp(-1.0, 1.0)).acos() / 2.0;
+        let distance = radius / half_angle.tan();
+        let (t0, t2) = (p1 + u0 * distance, p1 + u2 * distance);
+
+        self.line_to(t0.x, t0.y);
+        self.arc_to(radius, radius, 0.0, false, cross < 0.0, t2.x, t2.y);
+    }
+
+    // The raqote arc, a circular arc around a center that starts with a line from the
+    // current point.
+    fn arc(&mut self, x: f32, y: f32, radius: f32, start_angle: f32, sweep_angle: f32) {
+        self.begin_segment();
+        self.builder.arc(x, y, radius, start_angle, sweep_angle);
+        let end_angle = start_angle + sweep_angle;
+        let end = Point::new(x + radius * end_angle.cos(), y + radius * end_angle.sin());
+        self.end_segment(end, None, None);
+    }
+
+    fn close(&mut self) {
+        self.builder.close();
+        self.needs_move = true;
+        self.current_pos = self.start_pos;
+        self.last_cubic_ctrl = None;
+        self.last_quad_ctrl = None;
+    }
+
+    // An ellipse as a closed subpath of four quarter arcs, starting at its rightmost point.
+    fn add_ellipse(&mut self, cx: f32, cy: f32, rx: f32, ry: f32) {
+        self.move_to(cx + rx, cy);
+        self.arc_to(rx, ry, 0.0, false, true, cx, cy + ry);
+        self.arc_to(rx, ry, 0.0, false, true, cx - rx, cy);
+        self.arc_to(rx, ry, 0.0, false, true, cx, cy - ry);
+        self.arc_to(rx, ry, 0.0, false, true, cx + rx, cy);
+        self.close();
+    }
+
+    fn add_polygon(&mut self, points: &[Point], closed: bool) {
+        let Some((first, rest)) = points.split_first() else {
+            return;
+        };
+        self.move_to(first.x, first.y);
+        for p in rest {
+            self.line_to(p.x, p.y);
+        }
+        if closed {
+            self.close();
+        }
+    }
+
+    // A rectangle with elliptical corners, clockwise from the top-left corner. `radii` are
+    // the top-left, top-right, bottom-right and bottom-left radii, which must already fit.
+    fn add_rounded_rect(&mut self, rect: rq_rect, radii: [Point; 4]) {
+        let (x, y, width, height) = (rect.x, rect.y, rect.width, rect.height);
+        let [tl, tr, br, bl] = radii;
+
+        self.move_to(x + tl.x, y);
+
+        self.line_to(x + width - tr.x, y);
+        self.arc_to(tr.x, tr.y, 0.0, false, true, x + width, y + tr.y);
+
+        self.line_to(x + width, y + height - br.y);
+        self.arc_to(br.x, br.y, 0.0, false, true, x + width - br.x, y + height);
+
+        self.line_to(x + bl.x, y + height);
+        self.arc_to(bl.x, bl.y, 0.0, false, true, x, y + height - bl.y);
+
+        self.line_to(x, y + tl.y);
+        self.arc_to(tl.x, tl.y, 0.0, false, true, x + tl.x, y);
+
+        self.close();
+    }
+
+    fn append_path(&mut self, path: &Path, transform: &Transform) {
+        let map = |p: Point| transform.transform_point(p);
+
+        for op in &path.ops {
+            match *op {
+                PathOp::MoveTo(p) => {
+                    let p = map(p);
+                    self.move_to(p.x, p.y);
+                }
+                PathOp::LineTo(p) => {
+                    let p = map(p);
+                    self.line_to(p.x, p.y);
+                }
+                PathOp::QuadTo(c, p) => {
+                    let (c, p) = (map(c), map(p));
+                    self.quad_to(c.x, c.y, p.x, p.y);
+                }
+                PathOp::CubicTo(c1, c2, p) => {
+                    let (c1, c2, p) = (map(c1), map(c2), map(p));
+                    self.cubic_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y);
+                }
+                PathOp::Close => self.close(),
+            }
+        }
+    }
+
+    fn finish(self) -> Path {
+        self.builder.finish()
+    }
+}
+
+// SVG path data
+/// Parses SVG path data, such as the `d` attribute of a `<path>` element, into a path
+/// with the given fill rule. Returns null on failure; a syntax error fails with `Parse`
+/// and `rq_last_error_offset` reports the byte offset where parsing stopped.
+#[no_mangle]
+pub unsafe extern "C" fn rq_path_from_svg(
+    d: *const c_char,
+    fill_rule: rq_fill_rule,
+) -> *mut rq_path {
+    ffi_pointer(|| {
+        if d.is_null() {
+            return Err(Error::null_pointer("d"));
+        }
+
+        let mut path = SvgPathParser::new(std::ffi::CStr::from_ptr(d).to_bytes()).parse()?;
+        path.winding = fill_rule.into();
+        Ok(Box::into_raw(Box::new(rq_path::new(path))))
+    })
+}
+
+struct SvgPathParser<'a> {
+    data: &'a [u8],
+    pos: usize,
+    builder: rq_path_builder,
+}
+
+impl<'a> SvgPathParser<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            builder: rq_path_builder::new(),
+        }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error {
+            offset: Some(self.pos),
+            ..Error::new(rq_status::Parse, format!("{} at byte {}", message, self.pos))
+        }
+    }
+
+    fn parse(mut self) -> Result<Path, Error> {
+        self.skip_whitespace();
+        while let Some(&command) = self.data.get(self.pos) {
+            if !command.is_ascii_alphabetic() {
+                return Err(self.error("expected a path command"));
+            }
+            if self.builder.current_pos.is_none() && !matches!(command, b'M' | b'm') {
+                return Err(self.error("path data must start with a moveto command"));
+            }
+            self.pos += 1;
+            self.parse_command(command)?;
+            self.skip_whitespace();
+        }
+        Ok(self.builder.finish())
+    }
+
+    fn parse_command(&mut self, command: u8) -> Result<(), Error> {
+        if matches!(command, b'Z' | b'z') {
+            self.builder.close();
+            return Ok(());
+        }
+
+        let relative = command.is_ascii_lowercase();
+        let mut command = command.to_ascii_uppercase();
+        // Whitespace may separate the command from its first argument, but a comma may not.
+        self.skip_whitespace();
+        if self.data.get(self.pos) == Some(&b',') {
+            return Err(self.error("expected a number"));
+        }
+        // Every command takes at least one set of arguments, and further sets repeat the
+        // command implicitly.
+        loop {
+            let origin = match (relative, self.builder.current_pos) {
+                (true, Some(current)) => current,
+                _ => Point::new(0.0, 0.0),
+            };
+            self.parse_segment(command, origin)?;
+
+            // Coordinates following a moveto are implicit linetos.
+            if command == b'M' {
+                command = b'L';
+            }
+            let comma = self.skip_comma_whitespace();
+            if !self.at_number() {
+                return match comma {
+                    true => Err(self.error("expected a number")),
+                    false => Ok(()),
+                };
+            }
+        }
+    }
+
+    fn parse_segment(&mut self, command: u8, origin: Point) -> Result<(), Error> {
+        let current = self.builder.current_or_origin();
+        match command {
+            b'M' => {
+                let p = self.parse_point(origin)?;
+                self.builder.move_to(p.x, p.y);
+            }
+            b'L' => {
+                let p = self.parse_point(origin)?;
+                self.builder.line_to(p.x, p.y);
+            }
+            b'H' => {
+                let x = self.parse_number()? + origin.x;
+                self.builder.line_to(x, current.y);
+            }
+            b'V' => {
+                let y = self.parse_number()? + origin.y;
+                self.builder.line_to(current.x, y);
+            }
+            b'C' => {
+                let c1 = self.parse_point(origin)?;
+                let c2 = self.parse_point(origin)?;
+                let p = self.parse_point(origin)?;
+                self.builder.cubic_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y);
+            }
+            b'S' => {
+                let c2 = self.parse_point(origin)?;
+                let p = self.parse_point(origin)?;
+                self.builder.smooth_cubic_to(c2.x, c2.y, p.x, p.y);
+            }
+            b'Q' => {
+                let c = self.parse_point(origin)?;
+                let p = self.parse_point(origin)?;
+                self.builder.quad_to(c.x, c.y, p.x, p.y);
+            }
+            b'T' => {
+                let p = self.parse_point(origin)?;
+                self.builder.smooth_quad_to(p.x, p.y);
+            }
+            b'A' => {
+                let rx = self.parse_number()?.abs();
+                self.skip_comma_whitespace();
+                let ry = self.parse_number()?.abs();
+                self.skip_comma_whitespace();
+                let x_axis_rotation = self.parse_number()?;
+                self.skip_comma_whitespace();
+                let large_arc = self.parse_flag()?;
+                self.skip_comma_whitespace();
+                let sweep = self.parse_flag()?;
+                self.skip_comma_whitespace();
+                let p = self.parse_point(origin)?;
+                self.builder.arc_to(rx, ry, x_axis_rotation, large_arc, sweep, p.x, p.y);
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("unknown path command"));
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_point(&mut self, origin: Point) -> Result<Point, Error> {
+        self.skip_comma_whitespace();
+        let x = self.parse_number()?;
+        self.skip_comma_whitespace();
+        let y = self.parse_number()?;
+        Ok(Point::new(origin.x + x, origin.y + y))
+    }
+
+    fn parse_number(&mut self) -> Result<f32, Error> {
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            let from = parser.pos;
+            while parser.data.get(parser.pos).is_some_and(u8::is_ascii_digit) {
+                parser.pos += 1;
+            }
+            parser.pos - from
+        };
+
+        if matches!(self.data.get(self.pos), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let mut mantissa_digits = digits(self);
+        if self.data.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            mantissa_digits += digits(self);
+        }
+        if mantissa_digits == 0 {
+            self.pos = start;
+            return Err(self.error("expected a number"));
+        }
+
+        // Only treat `e` as an exponent if digits follow, so "1e" is not misread.
+        if matches!(self.data.get(self.pos), Some(b'e' | b'E')) {
+            let mantissa_end = self.pos;
+            self.pos += 1;
+            if matches!(self.data.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if digits(self) == 0 {
+                self.pos = mantissa_end;
+            }
+        }
+
+        let text = std::str::from_utf8(&self.data[start..self.pos]).expect("number is ASCII");
+        match text.parse::<f32>() {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => {
+                self.pos = start;
+                Err(self.error("number is out of range"))
+            }
+        }
+    }
+
+    fn parse_flag(&mut self) -> Result<bool, Error> {
+        let flag = match self.data.get(self.pos) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err(self.error("expected an arc flag of 0 or 1")),
+        };
+        self.pos += 1;
+        Ok(flag)
+    }
+
+    fn at_number(&self) -> bool {
+        matches!(
+            self.data.get(self.pos),
+            Some(b'0'..=b'9' | b'+' | b'-' | b'.')
+        )
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.data.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    // Returns whether a comma was skipped.
+    fn skip_comma_whitespace(&mut self) -> bool {
+        self.skip_whitespace();
+        if self.data.get(self.pos) != Some(&b',') {
+            return false;
+        }
+        self.pos += 1;
+        self.skip_whitespace();
+        true
+    }
+}
+
+// Reflects a control point about `current`, falling back to `current` itself when the
+// previous segment has no matching control point.
+fn reflect(ctrl: Option<Point>, current: Point) -> Point {
+    match ctrl {
+        Some(ctrl) => Point::new(2.0 * current.x - ctrl.x, 2.0 * current.y - ctrl.y),
+        None => current,
+    }
+}
+
+/// Encodes a path as SVG path data using absolute commands, with coordinates rounded to
+/// `precision` decimal places (at most 9). Free the string with rq_string_destroy.
+#[no_mangle]
+pub unsafe extern "C" fn rq_path_to_svg(path: *const rq_path, precision: u32) -> *mut c_char {
+    ffi_pointer(|| {
+        let path = deref(path, "path")?;
+        let precision = precision.min(9) as usize;
+
+        let mut svg = String::new();
+        let mut push = |command: char, points: &[Point]| {
+            if !svg.is_empty() {
+                svg.push(' ');
+            }
+            svg.push(command);
+            for (i, point) in points.iter().enumerate() {
+                if i > 0 {
+                    svg.push(' ');
+                }
+                svg.push_str(&format_svg_number(point.x, precision));
+                svg.push(' ');
+                svg.push_str(&format_svg_number(point.y, precision));
+            }
+        };
+        for op in &path.0.ops {
+            match *op {
+                PathOp::MoveTo(p) => push('M', &[p]),
+                PathOp::LineTo(p) => push('L', &[p]),
+                PathOp::QuadTo(c, p) => push('Q', &[c, p]),
+                PathOp::CubicTo(c1, c2, p) => push('C', &[c1, c2, p]),
+                PathOp::Close => push('Z', &[]),
+            }
+        }
+
+        // Formatted numbers never contain a nul byte.
+        Ok(CString::new(svg).expect("no nul bytes").into_raw())
+    })
+}
+
+/// Frees a string returned by this library. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn rq_string_destroy(s: *mut c_char) {
+    if !s.is_null() {
+        let _ = CString::from_raw(s);
+    }
+}
+
+// Formats a number with at most `precision` decimal places, without trailing zeros or
+// a negative zero.
+fn format_svg_number(value: f32, precision: usize) -> String {
+    let mut s = format!("{:.*}", precision, value);
+    if s.contains('.') {
+        let trimmed = s.trim_end_matches('0').trim_end_matches('.').len();
+        s.truncate(trimmed);
+    }
+    if s == "-0" {
+        s.remove(0);
+    }
+    s
+}
+
+// Draw target functions
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_create(width: i32, height: i32) -> *mut rq_draw_target {
+    ffi_pointer(|| {
+        let size = pixel_count("draw target", width, height)?;
+        let dt = DrawTarget::from_backing(width, height, PixelBuffer::Owned(vec![0; size]));
+        Ok(Box::into_raw(Box::new(rq_draw_target {
+            target: dt,
+            stack: Vec::new(),
+        })))
+    })
+}
+
+/// Creates a draw target that renders straight into `data`, a caller-owned buffer of
+/// premultiplied ARGB32 pixels in native byte order. `stride` is in bytes and must be
+/// `width * 4`. The buffer is not copied or freed; it must stay valid and must not be
+/// accessed concurrently with drawing until `rq_draw_target_destroy` is called.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_create_from_buffer(
+    width: i32,
+    height: i32,
+    stride: usize,
+    data: *mut u32,
+) -> *mut rq_draw_target {
+    ffi_pointer(|| {
+        let size = pixel_count("draw target", width, height)?;
+        if stride != width as usize * 4 {
+            return Err(Error::invalid_argument(format!(
+                "stride {} must be {} bytes for a width of {}",
+                stride,
+                width as usize * 4,
+                width
+            )));
+        }
+        if data.is_null() {
+            return Err(Error::null_pointer("data"));
+        }
+        if data.align_offset(std::mem::align_of::<u32>()) != 0 {
+            return Err(Error::invalid_argument("`data` must be 4-byte aligned"));
+        }
+
+        let buffer = PixelBuffer::Borrowed { data, len: size };
+        Ok(Box::into_raw(Box::new(rq_draw_target {
+            target: DrawTarget::from_backing(width, height, buffer),
+            stack: Vec::new(),
+        })))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_destroy(dt: *mut rq_draw_target) {
+    if !dt.is_null() {
+        let _ = Box::from_raw(dt);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_clear(
+    dt: *mut rq_draw_target,
+    color: rq_color,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let solid_color: SolidSource = color.into();
+        dt.target.clear(solid_color);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_set_transform(
+    dt: *mut rq_draw_target,
+    transform: rq_transform,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        dt.target.set_transform(&transform.into());
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_get_transform(
+    dt: *const rq_draw_target,
+    out_transform: *mut rq_transform,
+) -> rq_status {
+    ffi_status(|| {
+        let dt = deref(dt, "dt")?;
+        *deref_mut(out_transform, "out_transform")? = (*dt.target.get_transform()).into();
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_translate(
+    dt: *mut rq_draw_target,
+    tx: f32,
+    ty: f32,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let transform = dt.target.get_transform().pre_translate(euclid::vec2(tx, ty));
+        dt.target.set_transform(&transform);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_scale(
+    dt: *mut rq_draw_target,
+    sx: f32,
+    sy: f32,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let transform = dt.target.get_transform().pre_scale(sx, sy);
+        dt.target.set_transform(&transform);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_rotate(dt: *mut rq_draw_target, angle: f32) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let transform = dt.target.get_transform().pre_rotate(euclid::Angle::radians(angle));
+        dt.target.set_transform(&transform);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_concat(
+    dt: *mut rq_draw_target,
+    transform: rq_transform,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let transform = Transform::from(transform).then(dt.target.get_transform());
+        dt.target.set_transform(&transform);
+        Ok(())
+    })
+}
+
+// Graphics state functions
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_save(dt: *mut rq_draw_target) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let transform = *dt.target.get_transform();
+        dt.stack.push(StackEntry::Save(transform));
+        Ok(())
+    })
+}
+
+/// Pops every clip and layer pushed since the matching save and restores the transform
+/// that was current at that point. Fails with `InvalidState`, leaving the draw target
+/// untouched, if there is no matching save.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_restore(dt: *mut rq_draw_target) -> rq_status {
+    ffi_draw(dt, |dt| {
+        if !dt.stack.iter().any(|entry| matches!(entry, StackEntry::Save(_))) {
+            return Err(Error::invalid_state("restore without a matching save"));
+        }
+
+        while let Some(entry) = dt.stack.pop() {
+            match entry {
+                StackEntry::Clip(_) => dt.target.pop_clip(),
+                StackEntry::Layer { .. } => dt.target.pop_layer(),
+                StackEntry::Save(transform) => {
+                    dt.target.set_transform(&transform);
+                    break;
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+// Clipping functions
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_push_clip_path(
+    dt: *mut rq_draw_target,
+    path: *const rq_path,
+    fill_rule: rq_fill_rule,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let mut path_with_winding = deref(path, "path")?.0.clone();
+        path_with_winding.winding = fill_rule.into();
+
+        dt.target.push_clip(&path_with_winding);
+        let transform = *dt.target.get_transform();
+        dt.stack.push(StackEntry::Clip(Clip::Path(path_with_winding, transform)));
+        Ok(())
+    })
+}
+
+/// Intersects the clip with `rect`, in user space. The rect must be finite and must not
+/// have a negative size.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_push_clip_rect(
+    dt: *mut rq_draw_target,
+    rect: rq_rect,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let finite = [rect.x, rect.y, rect.width, rect.height].iter().all(|v| v.is_finite());
+        if !finite || rect.width < 0.0 || rect.height < 0.0 {
+            return Err(Error::invalid_argument(
+                "clip rect must be finite and must not have a negative size",
+            ));
+        }
+        let target = &mut dt.target;
+
+        // raqote's rect clip is in device space and integer aligned, so anything else goes
+        // through a path clip which honors the current transform and antialiases the edges.
+        let integer_rect = rect.x.fract() == 0.0
+            && rect.y.fract() == 0.0
+            && rect.width.fract() == 0.0
+            && rect.height.fract() == 0.0;
+
+        let clip = if *target.get_transform() == Transform::identity() && integer_rect {
+            // raqote indexes its buffers with the clip rect, so keep it within the target.
+            let (width, height) = (target.width() as f32, target.height() as f32);
+            let rect = IntRect::new(
+                IntPoint::new(rect.x.clamp(0.0, width) as i32, rect.y.clamp(0.0, height) as i32),
+                IntPoint::new(
+                    (rect.x + rect.width).clamp(0.0, width) as i32,
+                    (rect.y + rect.height).clamp(0.0, height) as i32,
+                ),
+            );
+            target.push_clip_rect(rect);
+            Clip::Rect(rect)
+        } else {
+            let mut builder = PathBuilder::new();
+            builder.rect(rect.x, rect.y, rect.width, rect.height);
+            let path = builder.finish();
+            target.push_clip(&path);
+            Clip::Path(path, *target.get_transform())
+        };
+        dt.stack.push(StackEntry::Clip(clip));
+        Ok(())
+    })
+}
+
+/// Pops the most recently pushed clip. Fails with `InvalidState`, leaving the draw target
+/// untouched, if there is no clip to pop or a layer or save was pushed after the clip.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_pop_clip(dt: *mut rq_draw_target) -> rq_status {
+    ffi_draw(dt, |dt| {
+        if !matches!(dt.stack.last(), Some(StackEntry::Clip(_))) {
+            return Err(Error::invalid_state("pop_clip without a matching push_clip"));
+        }
+
+        dt.target.pop_clip();
+        dt.stack.pop();
+        Ok(())
+    })
+}
+
+// Layer functions
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_push_layer(
+    dt: *mut rq_draw_target,
+    opacity: f32,
+    blend_mode: rq_blend_mode,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        if opacity.is_nan() {
+            return Err(Error::invalid_argument("layer opacity must not be NaN"));
+        }
+
+        let opacity = opacity.clamp(0.0, 1.0);
+        let blend = blend_mode.into();
+        dt.target.push_layer_with_blend(opacity, blend);
+        dt.stack.push(StackEntry::Layer { opacity, blend });
+        Ok(())
+    })
+}
+
+/// Composites the most recently pushed layer onto the layer or target below it. Fails with
+/// `InvalidState`, leaving the draw target untouched, if there is no layer to pop or a clip
+/// or save was pushed after the layer.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_pop_layer(dt: *mut rq_draw_target) -> rq_status {
+    ffi_draw(dt, |dt| {
+        if !matches!(dt.stack.last(), Some(StackEntry::Layer { .. })) {
+            return Err(Error::invalid_state("pop_layer without a matching push_layer"));
+        }
+
+        dt.target.pop_layer();
+        dt.stack.pop();
+        Ok(())
+    })
+}
+
+// Drawing functions
+/// Deprecated: takes the rq_paint enum, use rq_draw_target_fill_path_with_paint instead.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_fill_path(
+    dt: *mut rq_draw_target,
+    path: *const rq_path,
+    paint: rq_paint,
+    fill_rule: rq_fill_rule,
+    options: *const rq_draw_options,
+) -> rq_status {
+    draw_fill_path(dt, path, &paint, fill_rule, options)
+}
+
+/// Like rq_draw_target_fill_path, with a paint handle.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_fill_path_with_paint(
+    dt: *mut rq_draw_target,
+    path: *const rq_path,
+    paint: *const rq_paint_t,
+    fill_rule: rq_fill_rule,
+    options: *const rq_draw_options,
+) -> rq_status {
+    draw_fill_path(dt, path, &paint, fill_rule, options)
+}
+
+unsafe fn draw_fill_path(
+    dt: *mut rq_draw_target,
+    path: *const rq_path,
+    paint: &impl PaintArg,
+    fill_rule: rq_fill_rule,
+    options: *const rq_draw_options,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let draw_options = if options.is_null() {
+            DrawOptions::new()
+        } else {
+            (&*options).into()
+        };
+
+        let path = deref(path, "path")?;
+        let path = path.with_winding(fill_rule.into());
+        with_paint_source(paint.resolve()?, |source| {
+            dt.target.fill(path, source, &draw_options)
+        })?;
+        Ok(())
+    })
+}
+
+/// Deprecated: takes the rq_paint enum, use rq_draw_target_stroke_path_with_paint instead.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_stroke_path(
+    dt: *mut rq_draw_target,
+    path: *const rq_path,
+    paint: rq_paint,
+    stroke_style: *const rq_stroke_style,
+    options: *const rq_draw_options,
+) -> rq_status {
+    draw_stroke_path(dt, path, &paint, stroke_style, options)
+}
+
+/// Like rq_draw_target_stroke_path, with a paint handle.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_stroke_path_with_paint(
+    dt: *mut rq_draw_target,
+    path: *const rq_path,
+    paint: *const rq_paint_t,
+    stroke_style: *const rq_stroke_style,
+    options: *const rq_draw_options,
+) -> rq_status {
+    draw_stroke_path(dt, path, &paint, stroke_style, options)
+}
+
+unsafe fn draw_stroke_path(
+    dt: *mut rq_draw_target,
+    path: *const rq_path,
+    paint: &impl PaintArg,
+    stroke_style: *const rq_stroke_style,
+    options: *const rq_draw_options,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let draw_options = if options.is_null() {
+            DrawOptions::new()
+        } else {
+            (&*options).into()
+        };
+
+        let path = deref(path, "path")?;
+        let style = deref(stroke_style, "stroke_style")?.to_stroke_style()?;
+
+        with_paint_source(paint.resolve()?, |source| {
+            dt.target.stroke(&path.0, source, &style, &draw_options)
+        })?;
+        Ok(())
+    })
+}
+
+/// Deprecated: takes the rq_paint enum, use rq_draw_target_fill_rect_with_paint instead.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_fill_rect(
+    dt: *mut rq_draw_target,
+    rect: rq_rect,
+    paint: rq_paint,
+    options: *const rq_draw_options,
+) -> rq_status {
+    draw_fill_rect(dt, rect, &paint, options)
+}
+
+/// Like rq_draw_target_fill_rect, with a paint handle.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_fill_rect_with_paint(
+    dt: *mut rq_draw_target,
+    rect: rq_rect,
+    paint: *const rq_paint_t,
+    options: *const rq_draw_options,
+) -> rq_status {
+    draw_fill_rect(dt, rect, &paint, options)
+}
+
+unsafe fn draw_fill_rect(
+    dt: *mut rq_draw_target,
+    rect: rq_rect,
+    paint: &impl PaintArg,
+    options: *const rq_draw_options,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let draw_options = if options.is_null() {
+            DrawOptions::new()
+        } else {
+            (&*options).into()
+        };
+
+        with_paint_source(paint.resolve()?, |source| {
+            dt.target.fill_rect(rect.x, rect.y, rect.width, rect.height, source, &draw_options)
+        })?;
+        Ok(())
+    })
+}
+
+/// Deprecated: takes the rq_paint enum, use rq_draw_target_stroke_rect_with_paint instead.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_stroke_rect(
+    dt: *mut rq_draw_target,
+    rect: rq_rect,
+    paint: rq_paint,
+    stroke_style: *const rq_stroke_style,
+    options: *const rq_draw_options,
+) -> rq_status {
+    draw_stroke_rect(dt, rect, &paint, stroke_style, options)
+}
+
+/// Like rq_draw_target_stroke_rect, with a paint handle.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_stroke_rect_with_paint(
+    dt: *mut rq_draw_target,
+    rect: rq_rect,
+    paint: *const rq_paint_t,
+    stroke_style: *const rq_stroke_style,
+    options: *const rq_draw_options,
+) -> rq_status {
+    draw_stroke_rect(dt, rect, &paint, stroke_style, options)
+}
+
+unsafe fn draw_stroke_rect(
+    dt: *mut rq_draw_target,
+    rect: rq_rect,
+    paint: &impl PaintArg,
+    stroke_style: *const rq_stroke_style,
+    options: *const rq_draw_options,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let draw_options = if options.is_null() {
+            DrawOptions::new()
+        } else {
+            (&*options).into()
+        };
+
+        // Create a path for the rectangle
+        let mut builder = PathBuilder::new();
+        builder.move_to(rect.x, rect.y);
+        builder.line_to(rect.x + rect.width, rect.y);
+        builder.line_to(rect.x + rect.width, rect.y + rect.height);
+        builder.line_to(rect.x, rect.y + rect.height);
+        builder.close();
+        let path = builder.finish();
+
+        let style = deref(stroke_style, "stroke_style")?.to_stroke_style()?;
+
+        with_paint_source(paint.resolve()?, |source| {
+            dt.target.stroke(&path, source, &style, &draw_options)
+        })?;
+        Ok(())
+    })
+}
+
+/// Fills `count` rects with one paint. Gives the same pixels as calling
+/// rq_draw_target_fill_rect for each rect, without rebuilding the paint every time.
+/// Deprecated: takes the rq_paint enum, use rq_draw_target_fill_rects_with_paint instead.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_fill_rects(
+    dt: *mut rq_draw_target,
+    rects: *const rq_rect,
+    count: usize,
+    paint: rq_paint,
+    options: *const rq_draw_options,
+) -> rq_status {
+    draw_fill_rects(dt, rects, count, &paint, options)
+}
+
+/// Like rq_draw_target_fill_rects, with a paint handle.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_fill_rects_with_paint(
+    dt: *mut rq_draw_target,
+    rects: *const rq_rect,
+    count: usize,
+    paint: *const rq_paint_t,
+    options: *const rq_draw_options,
+) -> rq_status {
+    draw_fill_rects(dt, rects, count, &paint, options)
+}
+
+unsafe fn draw_fill_rects(
+    dt: *mut rq_draw_target,
+    rects: *const rq_rect,
+    count: usize,
+    paint: &impl PaintArg,
+    options: *const rq_draw_options,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let draw_options = if options.is_null() {
+            DrawOptions::new()
+        } else {
+            (&*options).into()
+        };
+
+        let rects = array_arg(rects, count, "rects")?;
+        with_paint_source(paint.resolve()?, |source| {
+            for rect in rects {
+                dt.target.fill_rect(rect.x, rect.y, rect.width, rect.height, source, &draw_options);
+            }
+        })?;
+        Ok(())
+    })
+}
+
+/// Fills `count` paths, each with the paint at the same index in `paints`. Gives the same
+/// pixels as calling rq_draw_target_fill_path for each path. Nothing is drawn if any path
+/// is null; a paint that fails stops the batch at that entry.
+/// Deprecated: takes the rq_paint enum, use rq_draw_target_fill_paths_with_paints instead.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_fill_paths(
+    dt: *mut rq_draw_target,
+    paths: *const *const rq_path,
+    count: usize,
+    paints: *const rq_paint,
+    fill_rule: rq_fill_rule,
+    options: *const rq_draw_options,
+) -> rq_status {
+    draw_fill_paths(dt, paths, count, paints, fill_rule, options)
+}
+
+/// Like rq_draw_target_fill_paths, with an array of paint handles.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_fill_paths_with_paints(
+    dt: *mut rq_draw_target,
+    paths: *const *const rq_path,
+    count: usize,
+    paints: *const *const rq_paint_t,
+    fill_rule: rq_fill_rule,
+    options: *const rq_draw_options,
+) -> rq_status {
+    draw_fill_paths(dt, paths, count, paints, fill_rule, options)
+}
+
+unsafe fn draw_fill_paths<P: PaintArg>(
+    dt: *mut rq_draw_target,
+    paths: *const *const rq_path,
+    count: usize,
+    paints: *const P,
+    fill_rule: rq_fill_rule,
+    options: *const rq_draw_options,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let draw_options = if options.is_null() {
+            DrawOptions::new()
+        } else {
+            (&*options).into()
+        };
+
+        let paths = array_arg(paths, count, "paths")?
+            .iter()
+            .map(|&path| deref(path, "paths"))
+            .collect::<Result<Vec<_>, _>>()?;
+        let paints = array_arg(paints, count, "paints")?;
+        for (path, paint) in paths.into_iter().zip(paints) {
+            let path = path.with_winding(fill_rule.into());
+            with_paint_source(paint.resolve()?, |source| {
+                dt.target.fill(path, source, &draw_options)
+            })?;
+        }
+        Ok(())
+    })
+}
+
+/// Fills `path` once for each of `count` transforms, each applied on top of the current
+/// transform as rq_draw_target_concat would. Gives the same pixels as concatenating each
+/// transform, filling and restoring, and leaves the current transform unchanged.
+/// Deprecated: takes the rq_paint enum, use rq_draw_target_fill_path_instances_with_paint instead.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_fill_path_instances(
+    dt: *mut rq_draw_target,
+    path: *const rq_path,
+    transforms: *const rq_transform,
+    count: usize,
+    paint: rq_paint,
+    fill_rule: rq_fill_rule,
+    options: *const rq_draw_options,
+) -> rq_status {
+    draw_fill_path_instances(dt, path, transforms, count, &paint, fill_rule, options)
+}
+
+/// Like rq_draw_target_fill_path_instances, with a paint handle.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_fill_path_instances_with_paint(
+    dt: *mut rq_draw_target,
+    path: *const rq_path,
+    transforms: *const rq_transform,
+    count: usize,
+    paint: *const rq_paint_t,
+    fill_rule: rq_fill_rule,
+    options: *const rq_draw_options,
+) -> rq_status {
+    draw_fill_path_instances(dt, path, transforms, count, &paint, fill_rule, options)
+}
+
+unsafe fn draw_fill_path_instances(
+    dt: *mut rq_draw_target,
+    path: *const rq_path,
+    transforms: *const rq_transform,
+    count: usize,
+    paint: &impl PaintArg,
+    fill_rule: rq_fill_rule,
+    options: *const rq_draw_options,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let draw_options = if options.is_null() {
+            DrawOptions::new()
+        } else {
+            (&*options).into()
+        };
+
+        let path = deref(path, "path")?;
+        let transforms = array_arg(transforms, count, "transforms")?;
+        let ctm = *dt.target.get_transform();
+        let path = path.with_winding(fill_rule.into());
+        with_paint_source(paint.resolve()?, |source| {
+            for &transform in transforms {
+                dt.target.set_transform(&Transform::from(transform).then(&ctm));
+                dt.target.fill(path, source, &draw_options);
+            }
+        })?;
+        dt.target.set_transform(&ctm);
+        Ok(())
+    })
+}
+
+// Image drawing functions
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_draw_image(
+    dt: *mut rq_draw_target,
+    image: *const rq_image,
+    x: f32,
+    y: f32,
+    options: *const rq_draw_options,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let draw_options = if options.is_null() {
+            DrawOptions::new()
+        } else {
+            (&*options).into()
+        };
+
+        let image = deref(image, "image")?;
+        dt.target.draw_image_at(x, y, &image.as_image(), &draw_options);
+        Ok(())
+    })
+}
+
+/// Draws the `src_rect` portion of `image` scaled into `dst_rect`. Filtering only samples
+/// the pixels `src_rect` touches, so neighbouring parts of the image do not bleed in. Both
+/// rects must be finite with a positive size, and `src_rect` must overlap the image.
+///
+/// Keeping other pixels out means copying the part of the image `src_rect` touches on
+/// every call, unless it spans the full width of the image. The copy is skipped for
+/// nearest filtering when both rects have integer coordinates and the current transform
+/// is an integer translation, since nothing outside `src_rect` is sampled then.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_draw_image_rect(
+    dt: *mut rq_draw_target,
+    image: *const rq_image,
+    src_rect: rq_rect,
+    dst_rect: rq_rect,
+    filter_mode: rq_filter_mode,
+    options: *const rq_draw_options,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let image = deref(image, "image")?.as_image();
+        let draw_options = if options.is_null() {
+            DrawOptions::new()
+        } else {
+            (&*options).into()
+        };
+
+        for (name, rect) in [("src_rect", &src_rect), ("dst_rect", &dst_rect)] {
+            let finite = [rect.x, rect.y, rect.width, rect.height].iter().all(|v| v.is_finite());
+            if !(finite && rect.width > 0.0 && rect.height > 0.0) {
+                return Err(Error::invalid_argument(format!(
+                    "{} must be finite with a positive size",
+                    name
+                )));
+            }
+        }
+
+        if src_rect.x == 0.0
+            && src_rect.y == 0.0
+            && src_rect.width == image.width as f32
+            && src_rect.height == image.height as f32
+            && matches!(filter_mode, rq_filter_mode::Bilinear)
+        {
+            dt.target.draw_image_with_size_at(
+                dst_rect.width,
+                dst_rect.height,
+                dst_rect.x,
+                dst_rect.y,
+                &image,
+                &draw_options,
+            );
+            return Ok(());
+        }
+
+        // Crop to the pixels the source rectangle touches, so padding repeats its edge rather
+        // than sampling the rest of the image.
+        let x0 = (src_rect.x.floor().max(0.0) as i32).min(image.width);
+        let y0 = (src_rect.y.floor().max(0.0) as i32).min(image.height);
+        let x1 = ((src_rect.x + src_rect.width).ceil().max(0.0) as i32).min(image.width);
+        let y1 = ((src_rect.y + src_rect.height).ceil().max(0.0) as i32).min(image.height);
+        if x1 <= x0 || y1 <= y0 {
+            return Err(Error::invalid_argument("src_rect must overlap the image"));
+        }
+
+        // With nearest filtering only pixels partly covered by `dst_rect` can sample outside
+        // `src_rect`, and there are none when both land on whole device pixels.
+        let integer = |r: &rq_rect| [r.x, r.y, r.width, r.height].iter().all(|v| v.fract() == 0.0);
+        let t = dt.target.get_transform();
+        let integer_translation = (t.m11, t.m12, t.m21, t.m22) == (1.0, 0.0, 0.0, 1.0)
+            && t.m31.fract() == 0.0
+            && t.m32.fract() == 0.0;
+        let (x0, y0, x1, y1) = if matches!(filter_mode, rq_filter_mode::Nearest)
+            && integer(&src_rect)
+            && integer(&dst_rect)
+            && integer_translation
+        {
+            (0, 0, image.width, image.height)
+        } else {
+            (x0, y0, x1, y1)
+        };
+
+        let cropped: Vec<u32>;
+        let image = if (x0, y0, x1, y1) == (0, 0, image.width, image.height) {
+            image
+        } else if (x0, x1) == (0, image.width) {
+            // Whole rows are contiguous, so they are borrowed rather than copied.
+            let rows = (y0 * image.width) as usize..(y1 * image.width) as usize;
+            Image { width: image.width, height: y1 - y0, data: &image.data[rows] }
+        } else {
+            cropped = (y0..y1)
+                .flat_map(|y| {
+                    let row = (y * image.width) as usize;
+                    &image.data[row + x0 as usize..row + x1 as usize]
+                })
+                .copied()
+                .collect();
+            Image { width: x1 - x0, height: y1 - y0, data: &cropped }
+        };
+
+        // Maps the destination rectangle back onto the source rectangle in the cropped image.
+        let image_transform = Transform::translation(-dst_rect.x, -dst_rect.y)
+            .then_scale(src_rect.width / dst_rect.width, src_rect.height / dst_rect.height)
+            .then_translate(euclid::vec2(src_rect.x - x0 as f32, src_rect.y - y0 as f32));
+        let source = Source::Image(image, ExtendMode::Pad, filter_mode.into(), image_transform);
+        dt.target.fill_rect(
+            dst_rect.x,
+            dst_rect.y,
+            dst_rect.width,
+            dst_rect.height,
+            &source,
+            &draw_options,
+        );
+        Ok(())
+    })
+}
+
+/// Draws `image` with its top-left corner at the origin of `transform`, which is applied on
+/// top of the draw target's current transform.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_draw_image_transformed(
+    dt: *mut rq_draw_target,
+    image: *const rq_image,
+    transform: rq_transform,
+    filter_mode: rq_filter_mode,
+    options: *const rq_draw_options,
+) -> rq_status {
+    ffi_draw(dt, |dt| {
+        let draw_options = if options.is_null() {
+            DrawOptions::new()
+        } else {
+            (&*options).into()
+        };
+
+        let image = deref(image, "image")?.as_image();
+        let transform = Transform::from(transform);
+        // A singular transform collapses the image to nothing, so there is nothing to draw.
+        let image_transform = match transform.inverse() {
+            Some(inverse) => inverse,
+            None => return Ok(()),
+        };
+
+        let mut builder = PathBuilder::new();
+        builder.rect(0.0, 0.0, image.width as f32, image.height as f32);
+        let path = builder.finish().transform(&transform);
+
+        let source = Source::Image(image, ExtendMode::Pad, filter_mode.into(), image_transform);
+        dt.target.fill(&path, &source, &draw_options);
+        Ok(())
+    })
+}
+
+// Pixel data access
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_get_data(dt: *mut rq_draw_target) -> *mut rq_argb {
+    ffi_pointer(|| {
+        let data = deref(dt, "dt")?.target.get_data();
+        let mut buffer = vec![0; data.len() * 4];
+
+        // Convert from raqote format to BGRA format expected by blend2d
+        rq_pixel_format::Bgra8Premultiplied.convert_row(data, &mut buffer);
+
+        Ok(Box::into_raw(Box::new(rq_argb(buffer))))
+    })
+}
+
+/// Returns the draw target's live pixel buffer without copying it.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_get_pixel_view(
+    dt: *mut rq_draw_target,
+    out_view: *mut rq_pixel_view,
+) -> rq_status {
+    ffi_status(|| {
+        let dt = deref_mut(dt, "dt")?;
+        let out_view = deref_mut(out_view, "out_view")?;
+
+        let (width, height) = (dt.target.width(), dt.target.height());
+        *out_view = rq_pixel_view {
+            data: dt.target.get_data_u8_mut().as_mut_ptr(),
+            width,
+            height,
+            stride: width as usize * 4,
+            format: rq_pixel_format::native(),
+        };
+        Ok(())
+    })
+}
+
+/// Converts the pixels in `rect` into `dst`, whose rows are `dst_stride` bytes apart.
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_read_pixels(
+    dt: *const rq_draw_target,
+    rect: rq_int_rect,
+    format: rq_pixel_format,
+    dst: *mut u8,
+    dst_stride: usize,
+) -> rq_status {
+    ffi_status(|| {
+        let dt = deref(dt, "dt")?;
+        if dst.is_null() {
+            return Err(Error::null_pointer("dst"));
+        }
+
+        let (width, height) = (dt.target.width(), dt.target.height());
+        let in_bounds = rect.x >= 0
+            && rect.y >= 0
+            && rect.width >= 0
+            && rect.height >= 0
+            && rect.x.checked_add(rect.width).is_some_and(|right| right <= width)
+            && rect.y.checked_add(rect.height).is_some_and(|bottom| bottom <= height);
+        if !in_bounds {
+            return Err(Error::invalid_argument(format!(
+                "rect {}x{} at {},{} is outside the {}x{} draw target",
+                rect.width, rect.height, rect.x, rect.y, width, height
+            )));
+        }
+
+        let row_bytes = rect.width as usize * 4;
+        if dst_stride < row_bytes {
+            return Err(Error::invalid_argument(format!(
+                "dst_stride {} is smaller than a row of {} bytes",
+                dst_stride, row_bytes
+            )));
+        }
+
+        let data = dt.target.get_data();
+        for row in 0..rect.height as usize {
+            let start = (rect.y as usize + row) * width as usize + rect.x as usize;
+            let src = &data[start..start + rect.width as usize];
+            let dst = std::slice::from_raw_parts_mut(dst.add(row * dst_stride), row_bytes);
+            format.convert_row(src, dst);
+        }
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_argb_data(data: *const rq_argb) -> *const u8 {
+    ffi_value(std::ptr::null(), || {
+        Ok(deref(data, "data")?.0.as_ptr())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_argb_destroy(data: *mut rq_argb) {
+    if !data.is_null() {
+        let _ = Box::from_raw(data);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_width(dt: *const rq_draw_target) -> i32 {
+    ffi_value(0, || {
+        Ok(deref(dt, "dt")?.target.width())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_height(dt: *const rq_draw_target) -> i32 {
+    ffi_value(0, || {
+        Ok(deref(dt, "dt")?.target.height())
+    })
+}
+
+// PNG export
+#[cfg(feature = "png")]
+fn encode_png<W: std::io::Write>(
+    target: &DrawTarget<PixelBuffer>,
+    writer: W,
+) -> Result<(), Error> {
+    let (width, height) = (target.width(), target.height());
+    let mut pixels = vec![0; target.get_data().len() * 4];
+    rq_pixel_format::Rgba8.convert_row(target.get_data(), &mut pixels);
+
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(|error| match error {
+            png::EncodingError::IoError(error) => Error::io(error),
+            error => Error::new(rq_status::Encode, error.to_string()),
+        })
+}
+
+/// Writes the draw target's pixels, un-premultiplied, to a PNG file at the UTF-8 `path`.
+#[cfg(feature = "png")]
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_write_png(
+    dt: *const rq_draw_target,
+    path: *const c_char,
+) -> rq_status {
+    ffi_status(|| {
+        let dt = deref(dt, "dt")?;
+        if path.is_null() {
+            return Err(Error::null_pointer("path"));
+        }
+        let path = std::ffi::CStr::from_ptr(path)
+            .to_str()
+            .map_err(|_| Error::invalid_argument("`path` is not valid UTF-8"))?;
+
+        let file = std::fs::File::create(path).map_err(Error::io)?;
+        encode_png(&dt.target, std::io::BufWriter::new(file))
+    })
+}
+
+/// Encodes the draw target's pixels, un-premultiplied, as PNG into a new buffer. The buffer
+/// must be freed with `rq_png_buffer_destroy`.
+#[cfg(feature = "png")]
+#[no_mangle]
+pub unsafe extern "C" fn rq_draw_target_encode_png(
+    dt: *const rq_draw_target,
+    out_buf: *mut *mut u8,
+    out_len: *mut usize,
+) -> rq_status {
+    ffi_status(|| {
+        let dt = deref(dt, "dt")?;
+        let out_buf = deref_mut(out_buf, "out_buf")?;
+        let out_len = deref_mut(out_len, "out_len")?;
+
+        let mut encoded = Vec::new();
+        encode_png(&dt.target, &mut encoded)?;
+
+        let encoded = encoded.into_boxed_slice();
+        *out_len = encoded.len();
+        *out_buf = Box::into_raw(encoded) as *mut u8;
+        Ok(())
+    })
+}
+
+#[cfg(feature = "png")]
+#[no_mangle]
+pub unsafe extern "C" fn rq_png_buffer_destroy(buf: *mut u8, len: usize) {
+    if !buf.is_null() {
+        let _ = Box::from_raw(std::ptr::slice_from_raw_parts_mut(buf, len));
+    }
+}
+
+// Gradient functions
+#[no_mangle]
+pub unsafe extern "C" fn rq_linear_gradient_create(
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    spread: rq_spread_mode,
+) -> *mut rq_linear_gradient {
+    ffi_pointer(|| {
+        Ok(Arc::into_raw(Arc::new(rq_linear_gradient {
+            x0,
+            y0,
+            x1,
+            y1,
+            stops: GradientStops::new(),
+            spread: spread.into(),
+        })) as *mut _)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_radial_gradient_create(
+    x0: f32,
+    y0: f32,
+    r0: f32,
+    x1: f32,
+    y1: f32,
+    r1: f32,
+    spread: rq_spread_mode,
+) -> *mut rq_radial_gradient {
+    ffi_pointer(|| {
+        Ok(Arc::into_raw(Arc::new(rq_radial_gradient {
+            x0,
+            y0,
+            r0,
+            x1,
+            y1,
+            r1,
+            stops: GradientStops::new(),
+            spread: spread.into(),
+        })) as *mut _)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_linear_gradient_add_stop(
+    gradient: *mut rq_linear_gradient,
+    stop: rq_gradient_stop,
+) -> rq_status {
+    ffi_status(|| {
+        let gradient = deref(gradient, "gradient")?;
+        if !stop.position.is_finite() {
+            return Err(Error::invalid_argument("gradient stop position must be finite"));
+        }
+
+        gradient.stops.push(stop.into());
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_radial_gradient_add_stop(
+    gradient: *mut rq_radial_gradient,
+    stop: rq_gradient_stop,
+) -> rq_status {
+    ffi_status(|| {
+        let gradient = deref(gradient, "gradient")?;
+        if !stop.position.is_finite() {
+            return Err(Error::invalid_argument("gradient stop position must be finite"));
+        }
+
+        gradient.stops.push(stop.into());
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_linear_gradient_retain(
+    gradient: *mut rq_linear_gradient,
+) -> *mut rq_linear_gradient {
+    retain(gradient)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_linear_gradient_release(gradient: *mut rq_linear_gradient) {
+    release(gradient);
+}
+
+/// Equivalent to `rq_linear_gradient_release`.
+#[no_mangle]
+pub unsafe extern "C" fn rq_linear_gradient_destroy(gradient: *mut rq_linear_gradient) {
+    release(gradient);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_radial_gradient_retain(
+    gradient: *mut rq_radial_gradient,
+) -> *mut rq_radial_gradient {
+    retain(gradient)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_radial_gradient_release(gradient: *mut rq_radial_gradient) {
+    release(gradient);
+}
+
+/// Equivalent to `rq_radial_gradient_release`.
+#[no_mangle]
+pub unsafe extern "C" fn rq_radial_gradient_destroy(gradient: *mut rq_radial_gradient) {
+    release(gradient);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_sweep_gradient_create(
+    center_x: f32,
+    center_y: f32,
+    start_angle: f32,
+    end_angle: f32,
+    spread: rq_spread_mode,
+) -> *mut rq_sweep_gradient {
+    ffi_pointer(|| {
+        Ok(Arc::into_raw(Arc::new(rq_sweep_gradient {
+            center_x,
+            center_y,
+            start_angle,
+            end_angle,
+            stops: GradientStops::new(),
+            spread: spread.into(),
+        })) as *mut _)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_sweep_gradient_add_stop(
+    gradient: *mut rq_sweep_gradient,
+    stop: rq_gradient_stop,
+) -> rq_status {
+    ffi_status(|| {
+        let gradient = deref(gradient, "gradient")?;
+        if !stop.position.is_finite() {
+            return Err(Error::invalid_argument("gradient stop position must be finite"));
+        }
+
+        gradient.stops.push(stop.into());
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_sweep_gradient_retain(
+    gradient: *mut rq_sweep_gradient,
+) -> *mut rq_sweep_gradient {
+    retain(gradient)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_sweep_gradient_release(gradient: *mut rq_sweep_gradient) {
+    release(gradient);
+}
+
+/// Equivalent to `rq_sweep_gradient_release`.
+#[no_mangle]
+pub unsafe extern "C" fn rq_sweep_gradient_destroy(gradient: *mut rq_sweep_gradient) {
+    release(gradient);
+}
+
+// Pattern functions  
+#[no_mangle]
+pub unsafe extern "C" fn rq_image_create(
+    width: i32,
+    height: i32,
+    data: *const u32,
+) -> *mut rq_image {
+    ffi_pointer(|| {
+        let size = pixel_count("image", width, height)?;
+        if data.is_null() && size > 0 {
+            return Err(Error::null_pointer("data"));
+        }
+
+        let data = if size > 0 {
+            std::slice::from_raw_parts(data, size).to_vec()
+        } else {
+            Vec::new()
+        };
+
+        Ok(Arc::into_raw(Arc::new(rq_image {
+            width,
+            height,
+            data,
+        })) as *mut _)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_image_retain(image: *mut rq_image) -> *mut rq_image {
+    retain(image)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_image_release(image: *mut rq_image) {
+    release(image);
+}
+
+/// Equivalent to `rq_image_release`.
+#[no_mangle]
+pub unsafe extern "C" fn rq_image_destroy(image: *mut rq_image) {
+    release(image);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_pattern_create(
+    image: *mut rq_image,
+    extend_mode: rq_extend_mode,
+    filter_mode: rq_filter_mode,
+    transform: rq_transform,
+) -> *mut rq_pattern {
+    ffi_pointer(|| {
+        if image.is_null() {
+            return Err(Error::null_pointer("image"));
+        }
+
+        Ok(Arc::into_raw(Arc::new(rq_pattern {
+            image: {
+                Arc::increment_strong_count(image);
+                Arc::from_raw(image)
+            },
+            extend_mode: extend_mode.into(),
+            filter_mode: filter_mode.into(),
+            transform,
+        })) as *mut _)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_pattern_retain(pattern: *mut rq_pattern) -> *mut rq_pattern {
+    retain(pattern)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_pattern_release(pattern: *mut rq_pattern) {
+    release(pattern);
+}
+
+/// Equivalent to `rq_pattern_release`.
+#[no_mangle]
+pub unsafe extern "C" fn rq_pattern_destroy(pattern: *mut rq_pattern) {
+    release(pattern);
+}
+
+// Image decoding
+#[no_mangle]
+pub unsafe extern "C" fn rq_image_width(image: *const rq_image) -> i32 {
+    ffi_value(0, || Ok(deref(image, "image")?.width))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_image_height(image: *const rq_image) -> i32 {
+    ffi_value(0, || Ok(deref(image, "image")?.height))
+}
+
+/// Decodes a PNG or JPEG file at the UTF-8 `path` into a premultiplied image. Returns null
+/// on failure; `rq_last_error_status` tells decode errors (`Decode`) from I/O errors (`Io`).
+#[no_mangle]
+pub unsafe extern "C" fn rq_image_load_file(path: *const c_char) -> *mut rq_image {
+    ffi_pointer(|| {
+        if path.is_null() {
+            return Err(Error::null_pointer("path"));
+        }
+        let path = std::ffi::CStr::from_ptr(path)
+            .to_str()
+            .map_err(|_| Error::invalid_argument("`path` is not valid UTF-8"))?;
+
+        let bytes = std::fs::read(path).map_err(Error::io)?;
+        Ok(Arc::into_raw(Arc::new(decode_image(&bytes)?)) as *mut _)
+    })
+}
+
+/// Decodes PNG or JPEG data held in memory into a premultiplied image. Returns null on
+/// failure.
+#[no_mangle]
+pub unsafe extern "C" fn rq_image_load_memory(data: *const u8, len: usize) -> *mut rq_image {
+    ffi_pointer(|| {
+        if data.is_null() {
+            return Err(Error::null_pointer("data"));
+        }
+
+        let bytes = std::slice::from_raw_parts(data, len);
+        Ok(Arc::into_raw(Arc::new(decode_image(bytes)?)) as *mut _)
+    })
+}
+
+fn decode_image(bytes: &[u8]) -> Result<rq_image, Error> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        decode_png(bytes)
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        decode_jpeg(bytes)
+    } else {
+        Err(Error::decode("unrecognized image format, expected PNG or JPEG"))
+    }
+}
+
+// Builds an image from decoded rows of unpremultiplied 8-bit channels, `channels` bytes per
+// pixel: gray, gray and alpha, RGB or RGBA.
+#[cfg(any(feature = "decode-png", feature = "decode-jpeg"))]
+fn image_from_channels(
+    width: u32,
+    height: u32,
+    channels: usize,
+    bytes: &[u8],
+) -> Result<rq_image, Error> {
+    let (width, height) = match (i32::try_from(width), i32::try_from(height)) {
+        (Ok(width), Ok(height)) => (width, height),
+        _ => {
+            return Err(Error::overflow(format!(
+                "image size {}x{} is too large",
+                width, height
+            )))
+        }
+    };
+    let size = pixel_count("image", width, height)?;
+    if bytes.len() < size * channels {
+        return Err(Error::decode("decoded image data is truncated"));
+    }
+
+    let data = bytes
+        .chunks_exact(channels)
+        .take(size)
+        .map(|pixel| {
+            let (r, g, b, a) = match *pixel {
+                [l] => (l, l, l, 255),
+                [l, a] => (l, l, l, a),
+                [r, g, b] => (r, g, b, 255),
+                [r, g, b, a] => (r, g, b, a),
+                _ => unreachable!("unsupported channel count"),
+            };
+            SolidSource::from_unpremultiplied_argb(a, r, g, b).to_u32()
+        })
+        .collect();
+
+    Ok(rq_image { width, height, data })
+}
+
+#[cfg(feature = "decode-png")]
+fn decode_png(bytes: &[u8]) -> Result<rq_image, Error> {
+    // The data is already in memory, so read errors only mean truncated data.
+    let to_error = |error: png::DecodingError| Error::decode(error.to_string());
+
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(to_error)?;
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(to_error)?;
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => {
+            return Err(Error::decode("indexed PNG was not expanded to RGB"));
+        }
+    };
+
+    // Rows may be padded, so pack them before converting.
+    let row_bytes = info.width as usize * channels;
+    let packed: Vec<u8> = buffer
+        .chunks(info.line_size)
+        .take(info.height as usize)
+        .flat_map(|row| &row[..row_bytes])
+        .copied()
+        .collect();
+    image_from_channels(info.width, info.height, channels, &packed)
+}
+
+#[cfg(not(feature = "decode-png"))]
+fn decode_png(_bytes: &[u8]) -> Result<rq_image, Error> {
+    Err(Error::new(
+        rq_status::Unsupported,
+        "PNG decoding is not enabled, build with the `decode-png` feature",
+    ))
+}
+
+#[cfg(feature = "decode-jpeg")]
+fn decode_jpeg(bytes: &[u8]) -> Result<rq_image, Error> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let pixels = decoder.decode().map_err(|error| Error::decode(error.to_string()))?;
+    let info = decoder
+        .info()
+        .ok_or_else(|| Error::decode("JPEG has no image information"))?;
+
+    let (width, height) = (info.width as u32, info.height as u32);
+    match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => image_from_channels(width, height, 1, &pixels),
+        jpeg_decoder::PixelFormat::L16 => {
+            // Samples are in native byte order and hold `precision` bits each.
+            let shift = jpeg_precision(bytes).unwrap_or(16).saturating_sub(8);
+            let gray: Vec<u8> = pixels
+                .chunks_exact(2)
+                .map(|sample| (u16::from_ne_bytes([sample[0], sample[1]]) >> shift) as u8)
+                .collect();
+            image_from_channels(width, height, 1, &gray)
+        }
+        jpeg_decoder::PixelFormat::RGB24 => image_from_channels(width, height, 3, &pixels),
+        jpeg_decoder::PixelFormat::CMYK32 => {
+            let rgb: Vec<u8> = pixels
+                .chunks_exact(4)
+                .flat_map(|cmyk| {
+                    let k = 255 - cmyk[3] as u32;
+                    [0, 1, 2].map(|i| ((255 - cmyk[i] as u32) * k / 255) as u8)
+                })
+                .collect();
+            image_from_channels(width, height, 3, &rgb)
+        }
+    }
+}
+
+// Returns the sample precision in bits from a JPEG's frame header, which jpeg-decoder does
+// not report.
+#[cfg(feature = "decode-jpeg")]
+fn jpeg_precision(bytes: &[u8]) -> Option<u8> {
+    let mut pos = 2;
+    while let [0xff, marker, high, low, ..] = *bytes.get(pos..)? {
+        if matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+            return bytes.get(pos + 4).copied();
+        }
+        pos += 2 + u16::from_be_bytes([high, low]) as usize;
+    }
+    None
+}
+
+#[cfg(not(feature = "decode-jpeg"))]
+fn decode_jpeg(_bytes: &[u8]) -> Result<rq_image, Error> {
+    Err(Error::new(
+        rq_status::Unsupported,
+        "JPEG decoding is not enabled, build with the `decode-jpeg` feature",
+    ))
+}
+
+// Paint helper functions
+/// Deprecated along with the rq_paint enum; rq_paint_t handles use rq_paint_release.
+#[no_mangle]
+pub unsafe extern "C" fn rq_paint_destroy(paint: rq_paint) {
+    release_paint(&paint);
+}
+
+unsafe fn release_paint(paint: &rq_paint) {
+    match *paint {
+        rq_paint::Solid(_) => {
+            // Nothing to clean up for solid colors
+        },
+        rq_paint::LinearGradient(gradient) => release(gradient),
+        rq_paint::RadialGradient(gradient) => release(gradient),
+        rq_paint::SweepGradient(gradient) => release(gradient),
+        rq_paint::Pattern(pattern) => release(pattern),
+        rq_paint::Prepared(prepared) => release(prepared),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_paint_create_solid(color: rq_color) -> *mut rq_paint_t {
+    ffi_pointer(|| Ok(new_paint(rq_paint::Solid(color))))
+}
+
+/// Creates a paint from a linear gradient. The paint takes its own reference, so the
+/// caller still releases theirs. Stops added to the gradient later are used by the paint.
+#[no_mangle]
+pub unsafe extern "C" fn rq_paint_create_linear(
+    gradient: *mut rq_linear_gradient,
+) -> *mut rq_paint_t {
+    ffi_pointer(|| {
+        deref(gradient, "gradient")?;
+        Ok(new_paint(rq_paint::LinearGradient(retain(gradient))))
+    })
+}
+
+/// Creates a paint from a radial gradient. See rq_paint_create_linear.
+#[no_mangle]
+pub unsafe extern "C" fn rq_paint_create_radial(
+    gradient: *mut rq_radial_gradient,
+) -> *mut rq_paint_t {
+    ffi_pointer(|| {
+        deref(gradient, "gradient")?;
+        Ok(new_paint(rq_paint::RadialGradient(retain(gradient))))
+    })
+}
+
+/// Creates a paint from a sweep gradient. See rq_paint_create_linear.
+#[no_mangle]
+pub unsafe extern "C" fn rq_paint_create_sweep(
+    gradient: *mut rq_sweep_gradient,
+) -> *mut rq_paint_t {
+    ffi_pointer(|| {
+        deref(gradient, "gradient")?;
+        Ok(new_paint(rq_paint::SweepGradient(retain(gradient))))
+    })
+}
+
+/// Creates a paint from a pattern, taking its own reference to it.
+#[no_mangle]
+pub unsafe extern "C" fn rq_paint_create_pattern(pattern: *mut rq_pattern) -> *mut rq_paint_t {
+    ffi_pointer(|| {
+        deref(pattern, "pattern")?;
+        Ok(new_paint(rq_paint::Pattern(retain(pattern))))
+    })
+}
+
+/// Creates a paint from a prepared paint, taking its own reference to it.
+#[no_mangle]
+pub unsafe extern "C" fn rq_paint_create_prepared(
+    prepared: *mut rq_prepared_paint,
+) -> *mut rq_paint_t {
+    ffi_pointer(|| {
+        deref(prepared, "prepared")?;
+        Ok(new_paint(rq_paint::Prepared(retain(prepared))))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_paint_retain(paint: *mut rq_paint_t) -> *mut rq_paint_t {
+    retain(paint)
+}
+
+/// Releases a reference to a paint, and what it was created from once the last reference
+/// is gone.
+#[no_mangle]
+pub unsafe extern "C" fn rq_paint_release(paint: *mut rq_paint_t) {
+    release(paint);
+}
+
+// The Arc only counts references for the C API, it is never sent between threads here.
+#[allow(clippy::arc_with_non_send_sync)]
+fn new_paint(paint: rq_paint) -> *mut rq_paint_t {
+    Arc::into_raw(Arc::new(rq_paint_t(paint))) as *mut _
+}
+
+// Drawing functions accept paints either as the deprecated rq_paint enum, passed by value,
+// or as rq_paint_t handles.
+trait PaintArg {
+    unsafe fn resolve(&self) -> Result<&rq_paint, Error>;
+}
+
+impl PaintArg for rq_paint {
+    unsafe fn resolve(&self) -> Result<&rq_paint, Error> {
+        Ok(self)
+    }
+}
+
+impl PaintArg for *const rq_paint_t {
+    unsafe fn resolve(&self) -> Result<&rq_paint, Error> {
+        Ok(&deref(*self, "paint")?.0)
+    }
+}
+
+/// Builds the source for a paint once, for drawing with it many times through
+/// `rq_paint::Prepared`. The paint keeps its own reference and is still destroyed
+/// separately.
+/// Deprecated: takes the rq_paint enum, use rq_paint_prepare_handle instead.
+#[no_mangle]
+pub unsafe extern "C" fn rq_paint_prepare(paint: rq_paint) -> *mut rq_prepared_paint {
+    ffi_pointer(|| Ok(Arc::into_raw(Arc::new(rq_prepared_paint::new(&paint)?)) as *mut _))
+}
+
+/// Returns a new paint that draws like `paint` but whose source is built once, so drawing
+/// with it many times does not rebuild gradients. Like rq_paint_prepare, it is a snapshot
+/// of `paint`. Release it with rq_paint_release.
+#[no_mangle]
+pub unsafe extern "C" fn rq_paint_prepare_handle(paint: *const rq_paint_t) -> *mut rq_paint_t {
+    ffi_pointer(|| {
+        let prepared = rq_prepared_paint::new(&deref(paint, "paint")?.0)?;
+        let prepared = Arc::into_raw(Arc::new(prepared)) as *mut rq_prepared_paint;
+        Ok(new_paint(rq_paint::Prepared(prepared)))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_prepared_paint_retain(
+    paint: *mut rq_prepared_paint,
+) -> *mut rq_prepared_paint {
+    retain(paint)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rq_prepared_paint_release(paint: *mut rq_prepared_paint) {
+    release(paint);
+}
+
+/// Equivalent to `rq_prepared_paint_release`.
+#[no_mangle]
+pub unsafe extern "C" fn rq_prepared_paint_destroy(paint: *mut rq_prepared_paint) {
+    release(paint);
+}
+
+// Runs `f` with the source for a paint. Prepared paints are used as they are, anything
+// else is converted for this call.
+unsafe fn with_paint_source<R>(
+    paint: &rq_paint,
+    f: impl FnOnce(&Source) -> R,
+) -> Result<R, Error> {
+    match *paint {
+        rq_paint::Prepared(prepared) => Ok(deref(prepared, "paint")?.with_source(f)),
+        _ => Ok(rq_prepared_paint::new(paint)?.with_source(f)),
+    }
+}
+
+// Reference counting helpers for handles created with Arc::into_raw. Null is ignored so
+// C callers can release unconditionally.
+unsafe fn retain<T>(ptr: *mut T) -> *mut T {
+    if !ptr.is_null() {
+        Arc::increment_strong_count(ptr);
+    }
+    ptr
+}
+
+unsafe fn release<T>(ptr: *mut T) {
+    if !ptr.is_null() {
+        Arc::decrement_strong_count(ptr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    const BLACK: rq_color = rq_color { r: 0, g: 0, b: 0, a: 255 };
+
+    fn stroke_style(width: f32, dashes: &mut [f32], dash_offset: f32) -> rq_stroke_style {
+        rq_stroke_style {
+            width,
+            cap: rq_cap_style::Butt,
+            join: rq_join_style::Miter,
+            miter_limit: 10.0,
+            dash_array: dashes.as_mut_ptr(),
+            dash_array_length: dashes.len(),
+            dash_offset,
+        }
+    }
+
+    unsafe fn line(x0: f32, y0: f32, x1: f32, y1: f32) -> *mut rq_path {
+        let builder = rq_path_builder_create();
+        rq_path_builder_move_to(builder, x0, y0);
+        rq_path_builder_line_to(builder, x1, y1);
+        rq_path_builder_finish(builder)
+    }
+
+    unsafe fn alpha(dt: *mut rq_draw_target, x: i32, y: i32) -> u32 {
+        let width = (*dt).target.width();
+        (*dt).target.get_data()[(y * width + x) as usize] >> 24
+    }
+
+    #[test]
+    fn dashed_stroke_path() {
+        unsafe {
+            let dt = rq_draw_target_create(100, 10);
+            let path = line(0.0, 5.0, 100.0, 5.0);
+            let mut dashes = [10.0, 10.0];
+            let style = stroke_style(4.0, &mut dashes, 0.0);
+            let status =
+                rq_draw_target_stroke_path(dt, path, rq_paint::Solid(BLACK), &style, ptr::null());
+            assert_eq!(status, rq_status::Ok);
+
+            for x in [5, 25, 45, 65, 85] {
+                assert_eq!(alpha(dt, x, 5), 255, "dash at x={}", x);
+            }
+            for x in [15, 35, 55, 75, 95] {
+                assert_eq!(alpha(dt, x, 5), 0, "gap at x={}", x);
+            }
+            assert_eq!(alpha(dt, 5, 1), 0);
+
+            rq_path_destroy(path);
+            rq_draw_target_destroy(dt);
+        }
+    }
+
+    #[test]
+    fn dash_offset_shifts_pattern() {
+        unsafe {
+            let dt = rq_draw_target_create(100, 10);
+            let path = line(0.0, 5.0, 100.0, 5.0);
+            let mut dashes = [10.0, 10.0];
+            let style = stroke_style(4.0, &mut dashes, 10.0);
+            rq_draw_target_stroke_path(dt, path, rq_paint::Solid(BLACK), &style, ptr::null());
+
+            assert_eq!(alpha(dt, 5, 5), 0);
+            assert_eq!(alpha(dt, 15, 5), 255);
+
+            rq_path_destroy(path);
+            rq_draw_target_destroy(dt);
+        }
+    }
+
+    #[test]
+    fn dashed_stroke_rect() {
+        unsafe {
+            let dt = rq_draw_target_create(60, 60);
+            let mut dashes = [10.0, 10.0];
+            let style = stroke_style(2.0, &mut dashes, 0.0);
+            let rect = rq_rect { x: 10.0, y: 10.0, width: 40.0, height: 40.0 };
+            let status =
+                rq_draw_target_stroke_rect(dt, rect, rq_paint::Solid(BLACK), &style, ptr::null());
+            assert_eq!(status, rq_status::Ok);
+
+            // Along the top edge the dashes cover x 10..20 and 30..40.
+            assert_eq!(alpha(dt, 15, 10), 255);
+            assert_eq!(alpha(dt, 25, 10), 0);
+            assert_eq!(alpha(dt, 35, 10), 255);
+            assert_eq!(alpha(dt, 45, 10), 0);
+            // The interior is untouched.
+            assert_eq!(alpha(dt, 30, 30), 0);
+
+            rq_draw_target_destroy(dt);
+        }
+    }
+
+    #[test]
+    fn invalid_dash_array_is_rejected() {
+        unsafe {
+            let dt = rq_draw_target_create(10, 10);
+            let path = line(0.0, 5.0, 10.0, 5.0);
+            let mut dashes = [1.0, -1.0];
+            let style = stroke_style(1.0, &mut dashes, 0.0);
+            let status =
+                rq_draw_target_stroke_path(dt, path, rq_paint::Solid(BLACK), &style, ptr::null());
+            assert_eq!(status, rq_status::InvalidArgument);
+
+            rq_path_destroy(path);
+            rq_draw_target_destroy(dt);
+        }
+    }
+
+    #[test]
+    fn draw_image_rect_rejects_bad_rects() {
+        unsafe {
+            let dt = rq_draw_target_create(10, 10);
+            let pixels = [0xffff0000u32; 4];
+            let image = rq_image_create(2, 2, pixels.as_ptr());
+            let good = rq_rect { x: 0.0, y: 0.0, width: 2.0, height: 2.0 };
+            let bad = [
+                rq_rect { width: 0.0, ..good },
+                rq_rect { height: -1.0, ..good },
+                rq_rect { x: f32::NAN, ..good },
+                rq_rect { width: f32::INFINITY, ..good },
+            ];
+            for rect in bad {
+                for (src, dst) in [(rect, good), (good, rect)] {
+                    let status = rq_draw_target_draw_image_rect(
+                        dt,
+                        image,
+                        src,
+                        dst,
+                        rq_filter_mode::Bilinear,
+                        ptr::null(),
+                    );
+                    assert_eq!(status, rq_status::InvalidArgument);
+                }
+            }
+            let outside = rq_rect { x: 5.0, ..good };
+            let status = rq_draw_target_draw_image_rect(
+                dt,
+                image,
+                outside,
+                good,
+                rq_filter_mode::Bilinear,
+                ptr::null(),
+            );
+            assert_eq!(status, rq_status::InvalidArgument);
+            assert!((*dt).target.get_data().iter().all(|&p| p == 0));
+
+            rq_image_release(image);
+            rq_draw_target_destroy(dt);
+        }
+    }
+
+    #[test]
+    fn draw_image_rect_does_not_bleed() {
+        unsafe {
+            let dt = rq_draw_target_create(40, 10);
+            // Red on the left half, blue on the right.
+            let pixels = [0xffff0000u32, 0xffff0000, 0xff0000ff, 0xff0000ff];
+            let image = rq_image_create(4, 1, pixels.as_ptr());
+            let src = rq_rect { x: 0.0, y: 0.0, width: 2.0, height: 1.0 };
+            let dst = rq_rect { x: 0.0, y: 0.0, width: 40.0, height: 10.0 };
+            let status = rq_draw_target_draw_image_rect(
+                dt,
+                image,
+                src,
+                dst,
+                rq_filter_mode::Bilinear,
+                ptr::null(),
+            );
+            assert_eq!(status, rq_status::Ok);
+            for &pixel in (*dt).target.get_data() {
+                assert_eq!(pixel, 0xffff0000, "pixel {:08x}", pixel);
+            }
+
+            rq_image_release(image);
+            rq_draw_target_destroy(dt);
+        }
+    }
+
+    fn last_error() -> String {
+        let message = rq_last_error_message();
+        assert!(!message.is_null());
+        unsafe { std::ffi::CStr::from_ptr(message) }.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn panic_is_reported_as_status() {
+        let status = ffi_status(|| panic!("status payload"));
+        assert_eq!(status, rq_status::Panic);
+        assert_eq!(rq_last_error_status(), rq_status::Panic);
+        assert!(last_error().contains("status payload"));
+    }
+
+    #[test]
+    fn draw_target_survives_panic() {
+        unsafe {
+            let dt = rq_draw_target_create(20, 20);
+            let red = rq_paint::Solid(rq_color { r: 255, g: 0, b: 0, a: 255 });
+            let clip = rq_rect { x: 0.0, y: 0.0, width: 10.0, height: 20.0 };
+            assert_eq!(rq_draw_target_push_clip_rect(dt, clip), rq_status::Ok);
+            let transform = rq_transform_translate(5.0, 0.0);
+            assert_eq!(rq_draw_target_set_transform(dt, transform), rq_status::Ok);
+
+            let status = ffi_draw(dt, |dt| {
+                dt.target.set_transform(&Transform::scale(3.0, 3.0));
+                panic!("draw payload {}", 42)
+            });
+            assert_eq!(status, rq_status::Panic);
+            assert!(last_error().contains("draw payload 42"));
+
+            // The clip and transform from before the panic still apply.
+            let rect = rq_rect { x: -5.0, y: 0.0, width: 20.0, height: 20.0 };
+            let status = rq_draw_target_fill_rect(dt, rect, red, ptr::null());
+            assert_eq!(status, rq_status::Ok);
+            let data = (*dt).target.get_data();
+            assert_eq!(data[5 * 20 + 5], 0xffff0000);
+            assert_eq!(data[5 * 20 + 15], 0);
+            assert_eq!(rq_draw_target_pop_clip(dt), rq_status::Ok);
+
+            rq_draw_target_destroy(dt);
+        }
+    }
+
+    #[test]
+    fn recover_keeps_pixels_when_replay_panics() {
+        unsafe {
+            let dt = rq_draw_target_create(4, 4);
+            (*dt).target.get_data_mut()[5] = 0xff00ff00;
+            // A layer inside an inverted clip rect has a negative size, which makes raqote
+            // panic while the stack is replayed.
+            let inverted = IntRect::new(IntPoint::new(4, 0), IntPoint::new(0, 4));
+            (*dt).stack.push(StackEntry::Clip(Clip::Rect(inverted)));
+            (*dt).stack.push(StackEntry::Layer { opacity: 1.0, blend: BlendMode::SrcOver });
+
+            (*dt).recover(Transform::identity());
+            assert_eq!(((*dt).target.width(), (*dt).target.height()), (4, 4));
+            assert_eq!((*dt).target.get_data()[5], 0xff00ff00);
+            assert!((*dt).stack.is_empty());
+
+            rq_draw_target_destroy(dt);
+        }
+    }
+
+    // Fills a triangle whose hypotenuse runs diagonally across a 20x20 target and returns
+    // the alpha of every pixel.
+    unsafe fn fill_triangle(antialias: rq_antialias_mode) -> Vec<u32> {
+        let dt = rq_draw_target_create(20, 20);
+        let builder = rq_path_builder_create();
+        rq_path_builder_move_to(builder, 0.0, 0.0);
+        rq_path_builder_line_to(builder, 20.0, 0.0);
+        rq_path_builder_line_to(builder, 0.0, 13.0);
+        rq_path_builder_close(builder);
+        let path = rq_path_builder_finish(builder);
+        let options = rq_draw_options {
+            alpha: 1.0,
+            blend_mode: rq_blend_mode::SourceOver,
+            antialias,
+        };
+        let status = rq_draw_target_fill_path(
+            dt,
+            path,
+            rq_paint::Solid(BLACK),
+            rq_fill_rule::Winding,
+            &options,
+        );
+        assert_eq!(status, rq_status::Ok);
+        let alphas = (*dt).target.get_data().iter().map(|p| p >> 24).collect();
+        rq_path_destroy(path);
+        rq_draw_target_destroy(dt);
+        alphas
+    }
+
+    #[test]
+    fn antialias_none_has_hard_edges() {
+        unsafe {
+            let aliased = fill_triangle(rq_antialias_mode::None);
+            let smooth = fill_triangle(rq_antialias_mode::Gray);
+
+            assert!(aliased.iter().all(|&a| a == 0 || a == 255));
+            assert!(smooth.iter().any(|&a| a != 0 && a != 255));
+            // Well inside and well outside the edge both modes agree.
+            assert_eq!((aliased[2 * 20 + 2], smooth[2 * 20 + 2]), (255, 255));
+            assert_eq!((aliased[18 * 20 + 18], smooth[18 * 20 + 18]), (0, 0));
+            // Both cover the triangle's 130 pixels of area, give or take the rounding of
+            // the aliased edge.
+            let area = |alphas: &[u32]| alphas.iter().sum::<u32>() as f32 / 255.0;
+            assert!((area(&aliased) - 130.0).abs() < 10.0, "{}", area(&aliased));
+            assert!((area(&smooth) - 130.0).abs() < 5.0, "{}", area(&smooth));
+        }
+    }
+
+    #[test]
+    fn unpremultiply_clamps_channels() {
+        unsafe {
+            let dt = rq_draw_target_create(2, 1);
+            let data = (*dt).target.get_data_mut();
+            data[0] = 0x80ff0000;
+            data[1] = 0x80402000;
+            let rect = rq_int_rect { x: 0, y: 0, width: 2, height: 1 };
+            let mut out = [0u8; 8];
+            let status =
+                rq_draw_target_read_pixels(dt, rect, rq_pixel_format::Rgba8, out.as_mut_ptr(), 8);
+            assert_eq!(status, rq_status::Ok);
+            assert_eq!(out, [255, 0, 0, 128, 128, 64, 0, 128]);
+
+            rq_draw_target_destroy(dt);
+        }
+    }
+
+    #[test]
+    fn draw_target_renders_into_caller_buffer() {
+        unsafe {
+            let mut pixels = vec![0x12345678u32; 8 * 4];
+            let dt = rq_draw_target_create_from_buffer(8, 4, 8 * 4, pixels.as_mut_ptr());
+            assert!(!dt.is_null());
+
+            let red = rq_paint::Solid(rq_color { r: 255, g: 0, b: 0, a: 255 });
+            let rect = rq_rect { x: 2.0, y: 1.0, width: 4.0, height: 2.0 };
+            assert_eq!(rq_draw_target_fill_rect(dt, rect, red, ptr::null()), rq_status::Ok);
+            rq_draw_target_destroy(dt);
+
+            for y in 0..4 {
+                for x in 0..8 {
+                    let inside = (2..6).contains(&x) && (1..3).contains(&y);
+                    let expected = if inside { 0xffff0000 } else { 0x12345678 };
+                    assert_eq!(pixels[y * 8 + x], expected, "pixel {},{}", x, y);
+                }
+            }
+
+            let dt = rq_draw_target_create_from_buffer(8, 4, 8 * 4, pixels.as_mut_ptr());
+            assert_eq!(rq_draw_target_clear(dt, BLACK), rq_status::Ok);
+            rq_draw_target_destroy(dt);
+            assert!(pixels.iter().all(|&p| p == 0xff000000));
+        }
+    }
+
+    #[test]
+    fn disabled_decoders_are_unsupported() {
+        let png = b"\x89PNG\r\n\x1a\n";
+        let jpeg = [0xff, 0xd8, 0xff, 0xe0];
+        let cases = [
+            (&png[..], cfg!(feature = "decode-png")),
+            (&jpeg, cfg!(feature = "decode-jpeg")),
+        ];
+        for (bytes, enabled) in cases {
+            let image = unsafe { rq_image_load_memory(bytes.as_ptr(), bytes.len()) };
+            assert!(image.is_null());
+            let expected = if enabled { rq_status::Decode } else { rq_status::Unsupported };
+            assert_eq!(rq_last_error_status(), expected);
+        }
+    }
+
+    #[test]
+    fn svg_parse_error_offset() {
+        unsafe {
+            let d = b"M0 0 L10 10 X5\0";
+            let path = rq_path_from_svg(d.as_ptr().cast(), rq_fill_rule::Winding);
+            assert!(path.is_null());
+            assert_eq!(rq_last_error_status(), rq_status::Parse);
+            let mut offset = 0;
+            assert!(rq_last_error_offset(&mut offset));
+            assert_eq!(offset, 12);
+            assert!(last_error().contains("at byte 12"));
+
+            // Errors that are not about a position in the input have no offset.
+            assert!(rq_path_from_svg(ptr::null(), rq_fill_rule::Winding).is_null());
+            assert!(!rq_last_error_offset(&mut offset));
+        }
+    }
+
+    #[test]
+    fn path_queries_without_leading_move_to() {
+        unsafe {
+            // A builder that starts with close and line_to, which raqote fills from the first
+            // point of the first segment.
+            let builder = rq_path_builder_create();
+            rq_path_builder_close(builder);
+            rq_path_builder_line_to(builder, 0.0, 0.0);
+            rq_path_builder_line_to(builder, 10.0, 0.0);
+            rq_path_builder_line_to(builder, 10.0, 10.0);
+            rq_path_builder_line_to(builder, 0.0, 10.0);
+            let path = rq_path_builder_finish(builder);
+            // A segment after a close continues from the start of the closed subpath.
+            (*path).0.ops.push(PathOp::Close);
+            (*path).0.ops.push(PathOp::LineTo(Point::new(0.0, -5.0)));
+
+            let mut bounds = rq_rect { x: 0.0, y: 0.0, width: 0.0, height: 0.0 };
+            assert_eq!(rq_path_bounds(path, &mut bounds), rq_status::Ok);
+            assert_eq!(
+                (bounds.x, bounds.y, bounds.width, bounds.height),
+                (0.0, -5.0, 10.0, 15.0)
+            );
+            let (mut area, mut length) = (0.0, 0.0);
+            assert_eq!(rq_path_area(path, &mut area), rq_status::Ok);
+            assert_eq!(area, 100.0);
+            assert_eq!(rq_path_length(path, 0.1, &mut length), rq_status::Ok);
+            assert_eq!(length, 45.0);
+            let (mut contains, fill_rule) = (false, rq_fill_rule::Winding);
+            let inside = rq_point { x: 5.0, y: 5.0 };
+            let status = rq_path_contains_point(path, inside, fill_rule, &mut contains);
+            assert_eq!((status, contains), (rq_status::Ok, true));
+            let outside = rq_point { x: 5.0, y: -2.0 };
+            let status = rq_path_contains_point(path, outside, fill_rule, &mut contains);
+            assert_eq!((status, contains), (rq_status::Ok, false));
+
+            rq_path_destroy(path);
+        }
+    }
+
+    // Counts the bytes allocated on the current thread, for checking what a call allocates.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATED: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = ALLOCATED.try_with(|count| count.set(count.get() + layout.size()));
+            std::alloc::System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn allocated_by(f: impl FnOnce()) -> usize {
+        let before = ALLOCATED.with(|count| count.get());
+        f();
+        ALLOCATED.with(|count| count.get()) - before
+    }
+
+    // A self-intersecting path with many ops, which fills differently under each rule.
+    unsafe fn star_path(points: usize) -> *mut rq_path {
+        let builder = rq_path_builder_create();
+        for i in 0..points {
+            let angle = i as f32 * 0.01;
+            let x = 50.0 + 40.0 * angle.cos() * (angle * 0.37).sin();
+            rq_path_builder_line_to(builder, x, 50.0 + 40.0 * angle.sin());
+        }
+        rq_path_builder_close(builder);
+        rq_path_builder_finish(builder)
+    }
+
+    #[test]
+    fn fill_with_other_rule_matches_cloned_path() {
+        unsafe {
+            let path = star_path(2000);
+            for (fill_rule, winding) in [
+                (rq_fill_rule::EvenOdd, Winding::EvenOdd),
+                (rq_fill_rule::Winding, Winding::NonZero),
+            ] {
+                let dt = rq_draw_target_create(100, 100);
+                let paint = rq_paint::Solid(BLACK);
+                let status = rq_draw_target_fill_path(dt, path, paint, fill_rule, ptr::null());
+                assert_eq!(status, rq_status::Ok);
+
+                let mut expected = DrawTarget::new(100, 100);
+                let mut cloned = (*path).0.clone();
+                cloned.winding = winding;
+                let source = Source::Solid(SolidSource { r: 0, g: 0, b: 0, a: 255 });
+                expected.fill(&cloned, &source, &DrawOptions::new());
+                assert!((*dt).target.get_data() == expected.get_data());
+                rq_draw_target_destroy(dt);
+            }
+            rq_path_destroy(path);
+        }
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+    fn bench_fill_with_other_rule() {
+        unsafe {
+            let path = star_path(20000);
+            let dt = rq_draw_target_create(100, 100);
+            let fill = |fill_rule| {
+                rq_draw_target_fill_path(dt, path, rq_paint::Solid(BLACK), fill_rule, ptr::null());
+            };
+            // The first fill with the other rule makes the copy, later ones reuse it.
+            fill(rq_fill_rule::EvenOdd);
+
+            let same = allocated_by(|| fill(rq_fill_rule::Winding));
+            let other = allocated_by(|| fill(rq_fill_rule::EvenOdd));
+            let ops = (*path).0.ops.len() * std::mem::size_of::<PathOp>();
+            println!("bytes allocated per fill: {} same rule, {} other rule", same, other);
+            println!("a clone of the ops would add {} bytes", ops);
+            assert_eq!(other, same);
+
+            let rules = [("same", rq_fill_rule::Winding), ("other", rq_fill_rule::EvenOdd)];
+            for (name, fill_rule) in rules {
+                let start = std::time::Instant::now();
+                for _ in 0..100 {
+                    fill(fill_rule);
+                }
+                println!("{} rule: {:?} per fill", name, start.elapsed() / 100);
+            }
+
+            rq_draw_target_destroy(dt);
+            rq_path_destroy(path);
+        }
+    }
+
+    // Batch drawing is checked against drawing one at a time, with a partially transparent
+    // gradient, partial alpha and a non-identity transform so blending order matters.
+    const BATCH_OPTIONS: rq_draw_options = rq_draw_options {
+        alpha: 0.6,
+        blend_mode: rq_blend_mode::SourceOver,
+        antialias: rq_antialias_mode::Gray,
+    };
+
+    unsafe fn batch_gradient() -> *mut rq_linear_gradient {
+        let gradient = rq_linear_gradient_create(0.0, 0.0, 100.0, 100.0, rq_spread_mode::Reflect);
+        let red = rq_color { r: 255, g: 0, b: 0, a: 255 };
+        let blue = rq_color { r: 0, g: 0, b: 255, a: 128 };
+        rq_linear_gradient_add_stop(gradient, rq_gradient_stop { position: 0.0, color: red });
+        rq_linear_gradient_add_stop(gradient, rq_gradient_stop { position: 1.0, color: blue });
+        gradient
+    }
+
+    unsafe fn batch_target() -> *mut rq_draw_target {
+        let dt = rq_draw_target_create(100, 100);
+        rq_draw_target_set_transform(dt, rq_transform_translate(3.5, 1.25));
+        dt
+    }
+
+    unsafe fn svg(d: &str) -> *mut rq_path {
+        let d = CString::new(d).unwrap();
+        rq_path_from_svg(d.as_ptr(), rq_fill_rule::Winding)
+    }
+
+    unsafe fn assert_same_pixels(a: *mut rq_draw_target, b: *mut rq_draw_target) {
+        let (a, b) = ((*a).target.get_data(), (*b).target.get_data());
+        assert!(a.iter().any(|&p| p != 0));
+        assert!(a == b);
+    }
+
+    #[test]
+    fn fill_rects_matches_fill_rect() {
+        unsafe {
+            let gradient = batch_gradient();
+            let rects: Vec<rq_rect> = (0..200)
+                .map(|i| rq_rect {
+                    x: (i * 7 % 90) as f32 + 0.3,
+                    y: (i * 13 % 90) as f32,
+                    width: 5.5,
+                    height: 4.0,
+                })
+                .collect();
+
+            let (single, batch) = (batch_target(), batch_target());
+            for &rect in &rects {
+                let paint = rq_paint::LinearGradient(gradient);
+                rq_draw_target_fill_rect(single, rect, paint, &BATCH_OPTIONS);
+            }
+            let paint = rq_paint::LinearGradient(gradient);
+            let (count, options) = (rects.len(), &BATCH_OPTIONS);
+            let status = rq_draw_target_fill_rects(batch, rects.as_ptr(), count, paint, options);
+            assert_eq!(status, rq_status::Ok);
+            assert_same_pixels(single, batch);
+
+            rq_draw_target_destroy(single);
+            rq_draw_target_destroy(batch);
+            rq_linear_gradient_release(gradient);
+        }
+    }
+
+    #[test]
+    fn fill_paths_matches_fill_path() {
+        unsafe {
+            let gradient = batch_gradient();
+            let paths = [
+                svg("M10 10 L90 20 L50 90 Z M30 30 L60 30 L45 60 Z"),
+                svg("M0 0 C50 100 100 0 20 80 Z"),
+                svg("M5 95 L95 95 L50 5 Z"),
+            ];
+            let paints = || {
+                [
+                    rq_paint::Solid(rq_color { r: 10, g: 200, b: 30, a: 200 }),
+                    rq_paint::LinearGradient(gradient),
+                    rq_paint::Solid(rq_color { r: 200, g: 0, b: 99, a: 255 }),
+                ]
+            };
+
+            let (single, batch) = (batch_target(), batch_target());
+            for (path, paint) in paths.into_iter().zip(paints()) {
+                let fill_rule = rq_fill_rule::EvenOdd;
+                rq_draw_target_fill_path(single, path, paint, fill_rule, &BATCH_OPTIONS);
+            }
+            let status = rq_draw_target_fill_paths(
+                batch,
+                paths.map(|path| path as *const rq_path).as_ptr(),
+                paths.len(),
+                paints().as_ptr(),
+                rq_fill_rule::EvenOdd,
+                &BATCH_OPTIONS,
+            );
+            assert_eq!(status, rq_status::Ok);
+            assert_same_pixels(single, batch);
+
+            // A null path fails the whole batch before anything is drawn.
+            let dt = batch_target();
+            let with_null = [paths[0] as *const rq_path, ptr::null()];
+            let status = rq_draw_target_fill_paths(
+                dt,
+                with_null.as_ptr(),
+                2,
+                paints().as_ptr(),
+                rq_fill_rule::EvenOdd,
+                &BATCH_OPTIONS,
+            );
+            assert_eq!(status, rq_status::NullPointer);
+            assert!((*dt).target.get_data().iter().all(|&p| p == 0));
+
+            rq_draw_target_destroy(dt);
+            rq_draw_target_destroy(single);
+            rq_draw_target_destroy(batch);
+            for path in paths {
+                rq_path_destroy(path);
+            }
+            rq_linear_gradient_release(gradient);
+        }
+    }
+
+    #[test]
+    fn fill_path_instances_matches_concat() {
+        unsafe {
+            let gradient = batch_gradient();
+            let path = svg("M10 10 L90 20 L50 90 Z M30 30 L60 30 L45 60 Z");
+            let transforms: Vec<rq_transform> = (0..10)
+                .map(|i| {
+                    let offset = rq_transform_translate(i as f32 * 9.0, i as f32 * 5.0);
+                    rq_transform_multiply(rq_transform_scale(0.3, 0.3), offset)
+                })
+                .collect();
+
+            let (single, batch) = (batch_target(), batch_target());
+            for &transform in &transforms {
+                rq_draw_target_save(single);
+                rq_draw_target_concat(single, transform);
+                let paint = rq_paint::LinearGradient(gradient);
+                let fill_rule = rq_fill_rule::EvenOdd;
+                rq_draw_target_fill_path(single, path, paint, fill_rule, &BATCH_OPTIONS);
+                rq_draw_target_restore(single);
+            }
+            let status = rq_draw_target_fill_path_instances(
+                batch,
+                path,
+                transforms.as_ptr(),
+                transforms.len(),
+                rq_paint::LinearGradient(gradient),
+                rq_fill_rule::EvenOdd,
+                &BATCH_OPTIONS,
+            );
+            assert_eq!(status, rq_status::Ok);
+            assert_same_pixels(single, batch);
+            let (a, b) = (*(*single).target.get_transform(), *(*batch).target.get_transform());
+            assert_eq!(a, b);
+
+            rq_draw_target_destroy(single);
+            rq_draw_target_destroy(batch);
+            rq_path_destroy(path);
+            rq_linear_gradient_release(gradient);
+        }
+    }
+
+    // Parses SVG path data and writes it back out, or returns the parse error.
+    fn reencode_svg(d: &str) -> Result<String, String> {
+        unsafe {
+            let path = svg(d);
+            if path.is_null() {
+                return Err(last_error());
+            }
+            let encoded = rq_path_to_svg(path, 3);
+            let result = std::ffi::CStr::from_ptr(encoded).to_str().unwrap().to_owned();
+            rq_string_destroy(encoded);
+            rq_path_destroy(path);
+            Ok(result)
+        }
+    }
+
+    #[test]
+    fn svg_absolute_commands() {
+        assert_eq!(
+            reencode_svg("M10 20 L30 40 H50 V60 C1 2 3 4 5 6 S7 8 9 10 Q11 12 13 14 T15 16 Z"),
+            Ok("M10 20 L30 40 L50 40 L50 60 C1 2 3 4 5 6 C7 8 7 8 9 10 Q11 12 13 14 \
+                Q15 16 15 16 Z"
+                .into())
+        );
+    }
+
+    #[test]
+    fn svg_relative_commands() {
+        assert_eq!(
+            reencode_svg("m10 20 l20 20 h20 v20 c1 2 3 4 5 6 s2 2 4 4 q1 1 2 2 t2 2 z l1 1"),
+            Ok("M10 20 L30 40 L50 40 L50 60 C51 62 53 64 55 66 C57 68 57 68 59 70 \
+                Q60 71 61 72 Q62 73 63 74 Z M10 20 L11 21"
+                .into())
+        );
+    }
+
+    #[test]
+    fn svg_implicit_repeats() {
+        assert_eq!(reencode_svg("M0 0 10 10 20 0"), Ok("M0 0 L10 10 L20 0".into()));
+        assert_eq!(reencode_svg("m1 1 2 2 3 3"), Ok("M1 1 L3 3 L6 6".into()));
+        assert_eq!(reencode_svg("M0 0 H1 2 3 v1 1"), Ok("M0 0 L1 0 L2 0 L3 0 L3 1 L3 2".into()));
+        assert_eq!(
+            reencode_svg("M0 0 Q1 1 2 0 3 -1 4 0"),
+            Ok("M0 0 Q1 1 2 0 Q3 -1 4 0".into())
+        );
+    }
+
+    #[test]
+    fn svg_whitespace_and_commas() {
+        let expected = Ok("M0 0 L10 0 L10 10 L5 10 L5 5 Z".into());
+        for d in [
+            "M0 0 H 10 V 10 h -5 v -5 Z",
+            "M0,0H10V10h-5v-5Z",
+            " M 0 , 0\n\tH\t10\r\nV 10 h-5 v-5 z ",
+            "M0,0 L10,0,10,10 5,10 5 5 Z",
+        ] {
+            assert_eq!(reencode_svg(d), expected, "{:?}", d);
+        }
+        // Signs and a second decimal point start a new number.
+        assert_eq!(reencode_svg("M0,0L10-10.5.5.5"), Ok("M0 0 L10 -10.5 L0.5 0.5".into()));
+        assert_eq!(reencode_svg("M1e1-1E-1"), Ok("M10 -0.1".into()));
+        assert!(reencode_svg("M0 0 L1 1,").is_err());
+        assert!(reencode_svg("M0 0 L,1 1").is_err());
+    }
+
+    #[test]
+    fn svg_arcs() {
+        let spaced = reencode_svg("M0 0 a 5,5 0 1,0 10,0").unwrap();
+        for d in ["M0 0 a5 5 0 1 0 10 0", "M0 0a5,5,0,1,0,10,0", "M0 0a5 5 0 1010 0"] {
+            assert_eq!(reencode_svg(d).as_ref(), Ok(&spaced), "{:?}", d);
+        }
+        assert_eq!(reencode_svg("M0 0 A5 5 0 1 0 10 0").as_ref(), Ok(&spaced));
+        assert!(spaced.ends_with(" 10 0"), "{}", spaced);
+
+        unsafe {
+            // The large arc sweeping counterclockwise is the lower half of the circle.
+            let path = svg("M0 0 a5 5 0 1 0 10 0");
+            let mut bounds = rq_rect { x: 0.0, y: 0.0, width: 0.0, height: 0.0 };
+            assert_eq!(rq_path_bounds(path, &mut bounds), rq_status::Ok);
+            assert!((bounds.x, bounds.y) == (0.0, 0.0), "{:?}", (bounds.x, bounds.y));
+            assert!((bounds.width - 10.0).abs() < 0.01 && (bounds.height - 5.0).abs() < 0.01);
+            rq_path_destroy(path);
+        }
+
+        assert!(reencode_svg("M0 0 a5 5 0 2 0 10 0").is_err());
+        // Zero radii draw a straight line.
+        assert_eq!(reencode_svg("M0 0 A0 5 0 0 1 10 0"), Ok("M0 0 L10 0".into()));
+    }
+
+    #[cfg(feature = "decode-png")]
+    fn encode_png(
+        width: u32,
+        height: u32,
+        color: png::ColorType,
+        depth: png::BitDepth,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(depth);
+        encoder.write_header().unwrap().write_image_data(data).unwrap();
+        bytes
+    }
+
+    #[cfg(feature = "decode-png")]
+    #[test]
+    fn load_png_premultiplies() {
+        let rgba = [255, 0, 0, 128, 0, 255, 0, 255, 0, 0, 255, 0, 255, 255, 255, 255];
+        let bytes = encode_png(2, 2, png::ColorType::Rgba, png::BitDepth::Eight, &rgba);
+        unsafe {
+            let image = rq_image_load_memory(bytes.as_ptr(), bytes.len());
+            assert!(!image.is_null());
+            assert_eq!(((*image).width, (*image).height), (2, 2));
+            assert_eq!((*image).data, [0x80800000, 0xff00ff00, 0, 0xffffffff]);
+            rq_image_release(image);
+        }
+
+        // 16-bit samples are reduced to their high byte.
+        let gray = [0x80, 0x00, 0xff, 0xff];
+        let bytes = encode_png(2, 1, png::ColorType::Grayscale, png::BitDepth::Sixteen, &gray);
+        unsafe {
+            let image = rq_image_load_memory(bytes.as_ptr(), bytes.len());
+            assert!(!image.is_null());
+            assert_eq!((*image).data, [0xff808080, 0xffffffff]);
+            rq_image_release(image);
+        }
+    }
+
+    #[cfg(feature = "decode-jpeg")]
+    #[test]
+    fn jpeg_precision_reads_frame_header() {
+        // SOI, an APP0 segment, then a lossless SOF3 header with 12-bit samples.
+        let bytes = [
+            0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00, 0xff, 0xc3, 0x00, 0x0b, 0x0c,
+        ];
+        assert_eq!(jpeg_precision(&bytes), Some(12));
+        assert_eq!(jpeg_precision(&bytes[..8]), None);
+    }
+
+    #[test]
+    fn clip_rect_is_clamped_to_target() {
+        unsafe {
+            let red = rq_color { r: 255, g: 0, b: 0, a: 255 };
+            let full = rq_rect { x: 0.0, y: 0.0, width: 10.0, height: 10.0 };
+            for clip in [
+                rq_rect { x: 0.0, y: 0.0, width: 1e10, height: 1e10 },
+                rq_rect { x: -1e10, y: -5.0, width: 2e10, height: 20.0 },
+                rq_rect { x: 20.0, y: 20.0, width: 5.0, height: 5.0 },
+                rq_rect { x: 2.0, y: 2.0, width: 0.0, height: 0.0 },
+            ] {
+                let dt = rq_draw_target_create(10, 10);
+                assert_eq!(rq_draw_target_push_clip_rect(dt, clip), rq_status::Ok);
+                let blend = rq_blend_mode::SourceOver;
+                assert_eq!(rq_draw_target_push_layer(dt, 1.0, blend), rq_status::Ok);
+                let paint = rq_paint::Solid(red);
+                assert_eq!(rq_draw_target_fill_rect(dt, full, paint, ptr::null()), rq_status::Ok);
+                assert_eq!(rq_draw_target_pop_layer(dt), rq_status::Ok);
+                assert_eq!(rq_draw_target_pop_clip(dt), rq_status::Ok);
+
+                let inside = clip.x <= 0.0 && clip.x + clip.width >= 10.0;
+                let expected = if inside { 0xffff0000 } else { 0 };
+                assert!((*dt).target.get_data().iter().all(|&p| p == expected));
+                rq_draw_target_destroy(dt);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_clip_rects_are_rejected() {
+        unsafe {
+            let dt = rq_draw_target_create(10, 10);
+            let good = rq_rect { x: 0.0, y: 0.0, width: 5.0, height: 5.0 };
+            for clip in [
+                rq_rect { width: -1.0, ..good },
+                rq_rect { height: -1.0, ..good },
+                rq_rect { x: f32::NAN, ..good },
+                rq_rect { width: f32::INFINITY, ..good },
+            ] {
+                let status = rq_draw_target_push_clip_rect(dt, clip);
+                assert_eq!(status, rq_status::InvalidArgument);
+            }
+            assert!((*dt).stack.is_empty());
+            rq_draw_target_destroy(dt);
+        }
+    }
+
+    #[test]
+    fn prepared_paint_handle_draws_like_its_paint() {
+        unsafe {
+            let gradient = batch_gradient();
+            let paint = rq_paint_create_linear(gradient);
+            let prepared = rq_paint_prepare_handle(paint);
+            assert!(!prepared.is_null());
+            // The prepared paint keeps what it needs after the originals are released.
+            rq_paint_release(paint);
+            rq_linear_gradient_release(gradient);
+
+            let gradient = batch_gradient();
+            let paint = rq_paint_create_linear(gradient);
+            let rect = rq_rect { x: 5.0, y: 5.0, width: 80.0, height: 60.0 };
+            let (direct, cached) = (batch_target(), batch_target());
+            for _ in 0..2 {
+                rq_draw_target_fill_rect_with_paint(direct, rect, paint, &BATCH_OPTIONS);
+                rq_draw_target_fill_rect_with_paint(cached, rect, prepared, &BATCH_OPTIONS);
+            }
+            assert_same_pixels(direct, cached);
+
+            assert!(rq_paint_prepare_handle(ptr::null()).is_null());
+            assert_eq!(rq_last_error_status(), rq_status::NullPointer);
+
+            rq_draw_target_destroy(direct);
+            rq_draw_target_destroy(cached);
+            rq_paint_release(paint);
+            rq_paint_release(prepared);
+            rq_linear_gradient_release(gradient);
+        }
+    }
+
+    #[test]
+    fn path_query_failures_are_reported() {
+        unsafe {
+            let path = line(0.0, 0.0, 10.0, 0.0);
+            let (mut value, mut contains) = (-1.0, true);
+            let point = rq_point { x: 0.0, y: 0.0 };
+            let fill_rule = rq_fill_rule::Winding;
+
+            let status = rq_path_contains_point(ptr::null(), point, fill_rule, &mut contains);
+            assert_eq!((status, contains), (rq_status::NullPointer, true));
+            let status = rq_path_contains_point(path, point, fill_rule, ptr::null_mut());
+            assert_eq!(status, rq_status::NullPointer);
+            assert_eq!(rq_path_area(ptr::null(), &mut value), rq_status::NullPointer);
+            assert_eq!(rq_path_length(ptr::null(), 0.1, &mut value), rq_status::NullPointer);
+            assert_eq!(rq_path_length(path, 0.0, &mut value), rq_status::InvalidArgument);
+            assert_eq!(rq_path_length(path, f32::NAN, &mut value), rq_status::InvalidArgument);
+            assert_eq!(value, -1.0);
+
+            assert_eq!(rq_path_length(path, 0.1, &mut value), rq_status::Ok);
+            assert_eq!(value, 10.0);
+            rq_path_destroy(path);
+        }
+    }
+
+    #[test]
+    fn stroke_contains_point() {
+        unsafe {
+            let path = line(0.0, 0.0, 10.0, 0.0);
+            let style = stroke_style(4.0, &mut [], 0.0);
+            let mut contains = false;
+            let check = |x, y, contains: &mut bool| {
+                rq_path_stroke_contains_point(path, &style, rq_point { x, y }, contains)
+            };
+
+            assert_eq!(check(5.0, 1.5, &mut contains), rq_status::Ok);
+            assert!(contains);
+            assert_eq!(check(5.0, 2.5, &mut contains), rq_status::Ok);
+            assert!(!contains);
+
+            contains = true;
+            let point = rq_point { x: 5.0, y: 0.0 };
+            let status = rq_path_stroke_contains_point(ptr::null(), &style, point, &mut contains);
+            assert_eq!((status, contains), (rq_status::NullPointer, true));
+            let status = rq_path_stroke_contains_point(path, ptr::null(), point, &mut contains);
+            assert_eq!(status, rq_status::NullPointer);
+            let status = rq_path_stroke_contains_point(path, &style, point, ptr::null_mut());
+            assert_eq!(status, rq_status::NullPointer);
+
+            rq_path_destroy(path);
+        }
+    }
+
+    fn tile_color(column: u32, row: u32) -> u32 {
+        0xff000000 | ((column * 0x40) << 16) | ((row * 0x40) << 8)
+    }
+
+    // A 256x256 atlas of 64x64 tiles, each filled with a color made from its column and row.
+    fn atlas() -> Vec<u32> {
+        (0..256 * 256).map(|i| tile_color((i % 256) / 64, (i / 256) / 64)).collect()
+    }
+
+    #[test]
+    fn draw_image_rect_sprites() {
+        unsafe {
+            let pixels = atlas();
+            let image = rq_image_create(256, 256, pixels.as_ptr());
+            let tile = tile_color(1, 2);
+            let src = rq_rect { x: 64.0, y: 128.0, width: 64.0, height: 64.0 };
+            let draw = |dt, dst, filter_mode| {
+                let status =
+                    rq_draw_target_draw_image_rect(dt, image, src, dst, filter_mode, ptr::null());
+                assert_eq!(status, rq_status::Ok);
+            };
+
+            // Nearest filtering onto whole pixels samples the atlas without copying the tile.
+            let dt = rq_draw_target_create(100, 100);
+            rq_draw_target_set_transform(dt, rq_transform_translate(3.0, 4.0));
+            let dst = rq_rect { x: 7.0, y: 6.0, width: 32.0, height: 80.0 };
+            let allocated = allocated_by(|| draw(dt, dst, rq_filter_mode::Nearest));
+            assert!(allocated < 64 * 64 * 4, "{} bytes", allocated);
+            for (i, &pixel) in (*dt).target.get_data().iter().enumerate() {
+                let (x, y) = (i % 100, i / 100);
+                let inside = (10..42).contains(&x) && (10..90).contains(&y);
+                assert_eq!(pixel, if inside { tile } else { 0 }, "pixel {},{}", x, y);
+            }
+            rq_draw_target_destroy(dt);
+
+            // Other draws still keep the neighbouring tiles out.
+            let dst = rq_rect { x: 0.5, y: 0.25, width: 90.0, height: 90.0 };
+            for filter_mode in [rq_filter_mode::Nearest, rq_filter_mode::Bilinear] {
+                let dt = rq_draw_target_create(100, 100);
+                draw(dt, dst, filter_mode);
+                for &pixel in (*dt).target.get_data() {
+                    let alpha = pixel >> 24;
+                    for shift in [0, 8, 16] {
+                        let expected = ((tile >> shift) & 0xff) * alpha / 255;
+                        let channel = (pixel >> shift) & 0xff;
+                        assert!(channel.abs_diff(expected) <= 1, "pixel {:08x}", pixel);
+                    }
+                }
+                rq_draw_target_destroy(dt);
+            }
+
+            rq_image_release(image);
+        }
+    }
+
+    #[test]
+    fn draw_image_rect_borrows_whole_rows() {
+        unsafe {
+            let pixels = atlas();
+            let image = rq_image_create(256, 256, pixels.as_ptr());
+            let src = rq_rect { x: 0.0, y: 64.0, width: 256.0, height: 64.0 };
+            let dst = rq_rect { x: 0.0, y: 0.0, width: 100.0, height: 50.0 };
+            let dt = rq_draw_target_create(100, 50);
+            let allocated = allocated_by(|| {
+                let filter_mode = rq_filter_mode::Bilinear;
+                let status =
+                    rq_draw_target_draw_image_rect(dt, image, src, dst, filter_mode, ptr::null());
+                assert_eq!(status, rq_status::Ok);
+            });
+            assert!(allocated < 256 * 64 * 4, "{} bytes", allocated);
+            // Only the second row of tiles is drawn.
+            for &pixel in (*dt).target.get_data() {
+                assert_eq!((pixel >> 8) & 0xff, 0x40, "pixel {:08x}", pixel);
+            }
+            rq_draw_target_destroy(dt);
+            rq_image_release(image);
+        }
+    }
+}